@@ -2,7 +2,6 @@ use s_box::*;
 
 use blockmode::*;
 use na::{Matrix4};
-use std::collections::HashMap;
 
 type State = Matrix4<u8>;
 
@@ -231,18 +230,6 @@ fn inv_add_round_key(state: State, subkey: Matrix4<u8>) -> State {
     add_round_key(state, subkey)
 }
 
-// The higher the value of a key in the hashmap, the more that chunk appears
-// duplicated in the ciphertext, the more likely the use of ECB.
-pub fn detect_ecb(b: &[u8]) -> HashMap<&[u8], u32> {
-    let mut seen = HashMap::new();
-    for chunk in b.chunks(16) {
-        seen.entry(chunk)
-            .and_modify(|n| { *n += 1 })
-            .or_insert(1);
-    }
-    seen
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
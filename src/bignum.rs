@@ -0,0 +1,523 @@
+use codec::*;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Rem, Shl, Shr, Sub};
+
+// An unsigned integer of any size, for Diffie-Hellman, RSA and DSA. Limbs are
+// 32 bits so that products and carries fit in a u64, and are stored least
+// significant first with no zero limbs on top, so zero has no limbs at all
+// and equal numbers have equal limbs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    limbs: Vec<u32>
+}
+
+impl BigUint {
+    fn from_limbs(mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        BigUint { limbs: limbs }
+    }
+
+    pub fn zero() -> Self {
+        BigUint { limbs: Vec::new() }
+    }
+
+    pub fn one() -> Self {
+        BigUint::from_u64(1)
+    }
+
+    pub fn from_u64(n: u64) -> Self {
+        BigUint::from_limbs(vec![n as u32, (n >> 32) as u32])
+    }
+
+    // Big-endian, as numbers are written in the challenges.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut limbs = vec![0u32; bytes.len().div_ceil(4)];
+        for (i, &b) in bytes.iter().rev().enumerate() {
+            limbs[i / 4] |= (b as u32) << (8 * (i % 4));
+        }
+        BigUint::from_limbs(limbs)
+    }
+
+    // Big-endian with no leading zeros, so zero is empty.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.limbs.iter().rev()
+            .flat_map(|&limb| (0..4).rev().map(move |i| (limb >> (8 * i)) as u8))
+            .skip_while(|&b| b == 0)
+            .collect()
+    }
+
+    // Left-pads to `length` bytes, as RSA wants. Panics if it doesn't fit.
+    pub fn to_bytes_padded(&self, length: usize) -> Vec<u8> {
+        let bytes = self.to_bytes();
+        assert!(bytes.len() <= length, "{} bytes don't fit in {}", bytes.len(), length);
+        let mut out = vec![0; length - bytes.len()];
+        out.extend(bytes);
+        out
+    }
+
+    // Odd lengths are fine, since the NIST primes are written that way.
+    pub fn from_hex(hex: &[u8]) -> Result<Self, String> {
+        let mut even = if hex.len() % 2 == 1 { vec![b'0'] } else { Vec::new() };
+        even.extend_from_slice(hex);
+        Ok(BigUint::from_bytes(&base16_decode(&even)?))
+    }
+
+    pub fn to_hex(&self) -> Vec<u8> {
+        if self.is_zero() {
+            return b"0".to_vec();
+        }
+        let hex = base16_encode(&self.to_bytes());
+        if hex[0] == b'0' { hex[1..].to_vec() } else { hex }
+    }
+
+    pub fn from_decimal(decimal: &[u8]) -> Result<Self, String> {
+        if decimal.is_empty() {
+            return Err("Empty number".to_string());
+        }
+        let mut n = BigUint::zero();
+        for &digit in decimal {
+            if !digit.is_ascii_digit() {
+                return Err(format!("Invalid decimal digit: {}", digit as char));
+            }
+            n = n.mul_small(10).add_small((digit - b'0') as u32);
+        }
+        Ok(n)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_even(&self) -> bool {
+        self.limbs.first().map(|limb| limb & 1 == 0).unwrap_or(true)
+    }
+
+    // The number of bits needed to write it down; zero needs none.
+    pub fn bits(&self) -> usize {
+        match self.limbs.last() {
+            Some(top) => 32 * self.limbs.len() - top.leading_zeros() as usize,
+            None => 0
+        }
+    }
+
+    pub fn bit(&self, i: usize) -> bool {
+        self.limbs.get(i / 32).map(|limb| limb >> (i % 32) & 1 == 1).unwrap_or(false)
+    }
+
+    fn mul_small(&self, m: u32) -> Self {
+        let mut carry = 0u64;
+        let mut limbs: Vec<u32> = self.limbs.iter()
+            .map(|&limb| {
+                let product = limb as u64 * m as u64 + carry;
+                carry = product >> 32;
+                product as u32
+            })
+            .collect();
+        limbs.push(carry as u32);
+        BigUint::from_limbs(limbs)
+    }
+
+    fn add_small(&self, a: u32) -> Self {
+        self + &BigUint::from_u64(a as u64)
+    }
+
+    // Returns the quotient and the remainder.
+    fn divmod_small(&self, d: u32) -> (Self, u32) {
+        let mut rem = 0u64;
+        let mut limbs = vec![0; self.limbs.len()];
+        for i in (0..self.limbs.len()).rev() {
+            let n = rem << 32 | self.limbs[i] as u64;
+            limbs[i] = (n / d as u64) as u32;
+            rem = n % d as u64;
+        }
+        (BigUint::from_limbs(limbs), rem as u32)
+    }
+
+    pub fn checked_sub(&self, other: &BigUint) -> Option<Self> {
+        if *self < *other {
+            return None;
+        }
+        let mut borrow = 0i64;
+        let limbs = self.limbs.iter().enumerate()
+            .map(|(i, &limb)| {
+                let diff = limb as i64 - *other.limbs.get(i).unwrap_or(&0) as i64 - borrow;
+                borrow = if diff < 0 { 1 } else { 0 };
+                diff as u32
+            })
+            .collect();
+        Some(BigUint::from_limbs(limbs))
+    }
+
+    // Long division, as in Knuth's algorithm D (TAOCP 4.3.1): guess each
+    // quotient limb from the top two limbs of what's left and the top limb of
+    // the divisor, which is off by at most two once the divisor has been
+    // shifted to have its top bit set, and fix it up. Panics on zero.
+    pub fn divmod(&self, divisor: &BigUint) -> (Self, Self) {
+        assert!(!divisor.is_zero(), "division by zero");
+        if *self < *divisor {
+            return (BigUint::zero(), self.clone());
+        }
+        if divisor.limbs.len() == 1 {
+            let (q, r) = self.divmod_small(divisor.limbs[0]);
+            return (q, BigUint::from_u64(r as u64));
+        }
+
+        let shift = divisor.limbs.last().unwrap().leading_zeros() as usize;
+        let v = (divisor << shift).limbs;
+        let mut u = (self << shift).limbs;
+        u.push(0);
+        let n = v.len();
+        let b = 1u64 << 32;
+        let mut q = vec![0; u.len() - n];
+
+        for j in (0..u.len() - n).rev() {
+            let top = (u[j + n] as u64) << 32 | u[j + n - 1] as u64;
+            let mut qhat = top / v[n - 1] as u64;
+            let mut rhat = top % v[n - 1] as u64;
+            while qhat >= b || qhat * v[n - 2] as u64 > (rhat << 32 | u[j + n - 2] as u64) {
+                qhat -= 1;
+                rhat += v[n - 1] as u64;
+                if rhat >= b {
+                    break;
+                }
+            }
+
+            // u[j..] -= qhat * v
+            let mut borrow = 0i64;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let product = qhat * v[i] as u64 + carry;
+                carry = product >> 32;
+                let diff = u[i + j] as i64 - borrow - (product & 0xffff_ffff) as i64;
+                u[i + j] = diff as u32;
+                borrow = if diff < 0 { 1 } else { 0 };
+            }
+            let diff = u[j + n] as i64 - borrow - carry as i64;
+            u[j + n] = diff as u32;
+
+            // qhat was one too big, so add v back.
+            if diff < 0 {
+                qhat -= 1;
+                let mut carry = 0u64;
+                for i in 0..n {
+                    let sum = u[i + j] as u64 + v[i] as u64 + carry;
+                    u[i + j] = sum as u32;
+                    carry = sum >> 32;
+                }
+                u[j + n] = u[j + n].wrapping_add(carry as u32);
+            }
+            q[j] = qhat as u32;
+        }
+
+        u.truncate(n);
+        (BigUint::from_limbs(q), &BigUint::from_limbs(u) >> shift)
+    }
+
+    pub fn pow(&self, exp: u32) -> Self {
+        let mut out = BigUint::one();
+        for i in (0..32 - exp.leading_zeros()).rev() {
+            out = &out * &out;
+            if exp >> i & 1 == 1 {
+                out = &out * self;
+            }
+        }
+        out
+    }
+
+    // Square and multiply, from the top bit of `exp` down.
+    pub fn modpow(&self, exp: &BigUint, modulus: &BigUint) -> Self {
+        let base = self % modulus;
+        let mut out = &BigUint::one() % modulus;
+        for i in (0..exp.bits()).rev() {
+            out = &(&out * &out) % modulus;
+            if exp.bit(i) {
+                out = &(&out * &base) % modulus;
+            }
+        }
+        out
+    }
+
+    pub fn gcd(&self, other: &BigUint) -> Self {
+        let (mut a, mut b) = (self.clone(), other.clone());
+        while !b.is_zero() {
+            let r = &a % &b;
+            a = b;
+            b = r;
+        }
+        a
+    }
+
+    // The x with self * x = 1 (mod modulus), if there is one. This is the
+    // extended Euclidean algorithm, keeping only the coefficient of `self`,
+    // and keeping it mod `modulus` so it never goes negative.
+    pub fn modinv(&self, modulus: &BigUint) -> Option<Self> {
+        let (mut r0, mut r1) = (modulus.clone(), self % modulus);
+        let (mut t0, mut t1) = (BigUint::zero(), BigUint::one());
+        while !r1.is_zero() {
+            let (q, r) = r0.divmod(&r1);
+            let t = &(&t0 + modulus) - &(&(&q * &t1) % modulus);
+            r0 = r1;
+            r1 = r;
+            t0 = t1;
+            t1 = &t % modulus;
+        }
+        if r0 == BigUint::one() { Some(&t0 % modulus) } else { None }
+    }
+
+    // The largest r with r^n <= self, by Newton's method from above.
+    pub fn nth_root(&self, n: u32) -> Self {
+        assert!(n > 0, "zeroth root");
+        if self.is_zero() || n == 1 {
+            return self.clone();
+        }
+        let n_big = BigUint::from_u64(n as u64);
+        let mut x = &BigUint::one() << self.bits().div_ceil(n as usize);
+        loop {
+            let y = &(&x.mul_small(n - 1) + &(self / &x.pow(n - 1))) / &n_big;
+            if y >= x {
+                return x;
+            }
+            x = y;
+        }
+    }
+
+    pub fn cube_root(&self) -> Self {
+        self.nth_root(3)
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &BigUint) -> Ordering {
+        self.limbs.len().cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &BigUint) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// In decimal.
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        let mut chunks = Vec::new();
+        let mut n = self.clone();
+        while !n.is_zero() {
+            let (q, r) = n.divmod_small(1_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl Add<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let mut carry = 0u64;
+        let mut limbs: Vec<u32> = (0..self.limbs.len().max(other.limbs.len()))
+            .map(|i| {
+                let sum = *self.limbs.get(i).unwrap_or(&0) as u64
+                    + *other.limbs.get(i).unwrap_or(&0) as u64
+                    + carry;
+                carry = sum >> 32;
+                sum as u32
+            })
+            .collect();
+        limbs.push(carry as u32);
+        BigUint::from_limbs(limbs)
+    }
+}
+
+// Panics if the result would be negative; see checked_sub.
+impl Sub<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn sub(self, other: &BigUint) -> BigUint {
+        self.checked_sub(other).expect("attempt to subtract with overflow")
+    }
+}
+
+impl Mul<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let product = a as u64 * b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        BigUint::from_limbs(limbs)
+    }
+}
+
+impl Div<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn div(self, other: &BigUint) -> BigUint {
+        self.divmod(other).0
+    }
+}
+
+impl Rem<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn rem(self, other: &BigUint) -> BigUint {
+        self.divmod(other).1
+    }
+}
+
+impl Shl<usize> for &BigUint {
+    type Output = BigUint;
+
+    fn shl(self, bits: usize) -> BigUint {
+        let mut limbs = vec![0; bits / 32];
+        let shift = bits % 32;
+        if shift == 0 {
+            limbs.extend_from_slice(&self.limbs);
+        } else {
+            let mut carry = 0;
+            for &limb in &self.limbs {
+                limbs.push(limb << shift | carry);
+                carry = limb >> (32 - shift);
+            }
+            limbs.push(carry);
+        }
+        BigUint::from_limbs(limbs)
+    }
+}
+
+impl Shr<usize> for &BigUint {
+    type Output = BigUint;
+
+    fn shr(self, bits: usize) -> BigUint {
+        let skip = bits / 32;
+        let shift = bits % 32;
+        if skip >= self.limbs.len() {
+            return BigUint::zero();
+        }
+        let rest = &self.limbs[skip..];
+        let limbs = (0..rest.len())
+            .map(|i| {
+                if shift == 0 {
+                    rest[i]
+                } else {
+                    rest[i] >> shift | rest.get(i + 1).map(|&next| next << (32 - shift)).unwrap_or(0)
+                }
+            })
+            .collect();
+        BigUint::from_limbs(limbs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> BigUint {
+        BigUint::from_decimal(s.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_conversions() {
+        let n = BigUint::from_bytes(&[0, 0, 1, 2, 3, 4, 5]);
+        assert_eq!(n, BigUint::from_u64(0x0102030405));
+        assert_eq!(n.to_bytes(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(n.to_bytes_padded(8), vec![0, 0, 0, 1, 2, 3, 4, 5]);
+        assert_eq!(n.to_hex(), b"102030405".to_vec());
+        assert_eq!(BigUint::from_hex(b"102030405").unwrap(), n);
+        assert_eq!(n.to_string(), "4328719365");
+        assert_eq!(dec("4328719365"), n);
+        assert_eq!(n.bits(), 33);
+
+        assert_eq!(BigUint::from_bytes(&[]), BigUint::zero());
+        assert_eq!(BigUint::zero().to_bytes(), Vec::<u8>::new());
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert_eq!(BigUint::zero().to_hex(), b"0".to_vec());
+        assert_eq!(dec("1000000000000000000000").to_string(), "1000000000000000000000");
+        assert!(BigUint::from_decimal(b"12a").is_err());
+        assert!(BigUint::from_decimal(b"").is_err());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = dec("123456789012345678901234567890");
+        let b = dec("987654321098765432109876543210");
+        assert_eq!(&a + &b, dec("1111111110111111111011111111100"));
+        assert_eq!(&b - &a, dec("864197532086419753208641975320"));
+        assert_eq!(a.checked_sub(&b), None);
+        assert_eq!(&a * &b, dec("121932631137021795226185032733622923332237463801111263526900"));
+        assert_eq!(&b / &a, BigUint::from_u64(8));
+        assert_eq!(&b % &a, dec("9000000000900000000090"));
+        assert_eq!(&(&a << 100) >> 100, a);
+        assert_eq!(BigUint::from_u64(2).pow(100), &BigUint::one() << 100);
+        assert!(a < b);
+        assert!(b > a);
+
+        // (2^64 + 1)(2^64 - 1), split back up.
+        let x = &(&BigUint::one() << 128) - &BigUint::one();
+        let y = &(&BigUint::one() << 64) + &BigUint::one();
+        assert_eq!(x.divmod(&y), (BigUint::from_u64(u64::MAX), BigUint::zero()));
+    }
+
+    #[test]
+    fn test_divmod_identity() {
+        let mut n = dec("3");
+        for i in 1..40 {
+            let d = &dec("7919").pow(i % 7 + 1) + &BigUint::from_u64(i as u64);
+            let (q, r) = n.divmod(&d);
+            assert!(r < d);
+            assert_eq!(&(&q * &d) + &r, n);
+            n = &(&n * &dec("1000000007")) + &BigUint::from_u64(0xdead_beef);
+        }
+    }
+
+    #[test]
+    fn test_number_theory() {
+        assert_eq!(dec("1071").gcd(&dec("462")), dec("21"));
+        assert_eq!(dec("17").modinv(&dec("3120")), Some(dec("2753")));
+        assert_eq!(dec("6").modinv(&dec("9")), None);
+
+        // 2^89 - 1 is prime, so Fermat says a^(p-1) = 1.
+        let p = &(&BigUint::one() << 89) - &BigUint::one();
+        let a = dec("123456789123456789");
+        assert_eq!(a.modpow(&(&p - &BigUint::one()), &p), BigUint::one());
+        assert_eq!(dec("4").modpow(&dec("13"), &dec("497")), dec("445"));
+        assert_eq!(dec("4").modpow(&BigUint::zero(), &BigUint::one()), BigUint::zero());
+
+        let inv = a.modinv(&p).unwrap();
+        assert_eq!(&(&a * &inv) % &p, BigUint::one());
+    }
+
+    #[test]
+    fn test_roots() {
+        let n = dec("12345678901234567890");
+        let cube = n.pow(3);
+        assert_eq!(cube.cube_root(), n);
+        assert_eq!((&cube - &BigUint::one()).cube_root(), &n - &BigUint::one());
+        assert_eq!((&cube + &BigUint::one()).cube_root(), n);
+        assert_eq!(n.pow(5).nth_root(5), n);
+        assert_eq!(dec("99").nth_root(2), dec("9"));
+        assert_eq!(BigUint::zero().cube_root(), BigUint::zero());
+        assert_eq!(BigUint::one().nth_root(7), BigUint::one());
+    }
+}
@@ -0,0 +1,119 @@
+use ecb::*;
+use oracle::*;
+
+use itertools::zip;
+
+fn first_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+    zip(a, b).position(|(x, y)| x != y)
+}
+
+// Where in the plaintext our input ends up. We encrypt pairs of inputs that
+// only differ in their last byte, and look at where the ciphertexts diverge.
+// Under CTR that's exact. Under CBC it's only the block, so we push the
+// differing byte along until it spills into the next block. Both need the
+// oracle to reuse its IV or nonce.
+pub fn locate_input<O>(oracle: &O, mode: Mode) -> Result<usize, String>
+    where O: EncryptionOracle {
+    let differ = |k: usize| {
+        let mut x = vec![b'A'; k];
+        x.push(b'B');
+        let mut y = x.clone();
+        y[k] = b'C';
+        first_difference(&oracle.encrypt(&x), &oracle.encrypt(&y))
+            .ok_or_else(|| "Ciphertext doesn't depend on input".to_string())
+    };
+    match mode {
+        Mode::CTR => differ(0),
+        Mode::CBC => {
+            let block_size = ecb_block_size(oracle);
+            let first = differ(0)? / block_size;
+            for k in 1..=block_size {
+                if differ(k)? / block_size > first {
+                    return Ok((first + 1) * block_size - k);
+                }
+            }
+            Err("Couldn't find the start of the input".to_string())
+        },
+        _ => Err(format!("Can't bitflip {:?}", mode))
+    }
+}
+
+// Gets `target` into the decrypted plaintext without ever passing it to the
+// oracle. We pass in filler instead and XOR filler ^ target into the
+// ciphertext: in place under CTR, or into the block before under CBC, which
+// turns that block to garbage. If the garbage upsets `check` we try again
+// with different filler, which gives different garbage.
+pub fn bitflip<O, F>(oracle: &O, mode: Mode, target: &[u8], check: F) -> Result<Vec<u8>, String>
+    where O: EncryptionOracle, F: Fn(&[u8]) -> bool {
+    let start = locate_input(oracle, mode)?;
+    let (padding, offset) = match mode {
+        Mode::CBC => {
+            let block_size = ecb_block_size(oracle);
+            if target.len() > block_size {
+                return Err("Target must fit in a block".to_string());
+            }
+            let align = (block_size - start % block_size) % block_size;
+            (align + block_size, start + align)
+        },
+        _ => (0, start)
+    };
+
+    for filler in b'A'..=b'Z' {
+        let mut ct = oracle.encrypt(&vec![filler; padding + target.len()]);
+        for (i, b) in target.iter().enumerate() {
+            ct[offset + i] ^= filler ^ b;
+        }
+        if check(&ct) {
+            return Ok(ct);
+        }
+    }
+    Err("No forgery passed the check".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cookie::*;
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn test_locate_input() {
+        for &mode in &[Mode::CBC, Mode::CTR] {
+            for prefix in 0..40 {
+                let prefix_bytes: Vec<u8> = (0..prefix as u8).collect();
+                let oracle = Oracle::new(mode).with_prefix(&prefix_bytes).with_suffix(b"suffix");
+                assert_eq!(locate_input(&oracle, mode), Ok(prefix));
+            }
+        }
+        assert!(locate_input(&Oracle::new(Mode::ECB), Mode::ECB).is_err());
+    }
+
+    #[test]
+    fn test_bitflip() {
+        for &mode in &[Mode::CBC, Mode::CTR] {
+            for &prefix in &[0, 7, 16, 30] {
+                let oracle = Oracle::new(mode).with_prefix(&vec![b'x'; prefix]);
+                let encrypt = |userdata: &[u8]| oracle.encrypt(&comments_for(userdata));
+                let is_admin = |ct: &[u8]| {
+                    oracle.decrypt(ct).map(|pt| contains(&pt, b";admin=true;")).unwrap_or(false)
+                };
+                let forged = bitflip(&encrypt, mode, b";admin=true;", is_admin).unwrap();
+                assert!(is_admin(&forged));
+            }
+        }
+
+        // CTR leaves everything else intact, so the result still parses.
+        let oracle = Oracle::new(Mode::CTR);
+        let encrypt = |userdata: &[u8]| oracle.encrypt(&comments_for(userdata));
+        let forged = bitflip(&encrypt, Mode::CTR, b";admin=true", |_: &[u8]| true).unwrap();
+        let cookie = Cookie::parse(&oracle.decrypt(&forged).unwrap(), b';').unwrap();
+        assert_eq!(cookie.get(b"admin"), Some(&b"true"[..]));
+        assert_eq!(cookie.get(b"userdata"), Some(&b""[..]));
+
+        let oracle = Oracle::new(Mode::CBC);
+        assert!(bitflip(&oracle, Mode::CBC, &[0; 17], |_: &[u8]| true).is_err());
+    }
+}
@@ -2,6 +2,7 @@ use pkcs::*;
 
 use byteorder::{ByteOrder, LittleEndian};
 use itertools::zip;
+use std::collections::HashSet;
 
 type Result = ::std::result::Result<Vec<u8>, &'static str>;
 
@@ -88,6 +89,19 @@ pub fn cbc_decrypt<T>(cipher: &T, ct: &[u8], key: &[u8], iv: &[u8]) -> Result
     }
 }
 
+// CBC with the key doubling as the IV, which saves sending an IV along with
+// the ciphertext. It also gives the key away to anyone who can see a
+// decryption; see set_4::_27.
+pub fn cbc_encrypt_key_iv<T>(cipher: &T, pt: &[u8], key: &[u8]) -> Vec<u8>
+    where T: BlockCipher {
+    cbc_encrypt(cipher, pt, key, key)
+}
+
+pub fn cbc_decrypt_key_iv<T>(cipher: &T, ct: &[u8], key: &[u8]) -> Result
+    where T: BlockCipher {
+    cbc_decrypt(cipher, ct, key, key)
+}
+
 fn get_ctr_keystream<T>(cipher: &T, key: [u8; 16], nonce: [u8; 8], ctr: u64) -> [u8; 16]
     where T: BlockCipher {
     let mut buf = [0; 16];
@@ -109,6 +123,93 @@ pub fn ctr_encrypt<T>(cipher: &T, bytes: &[u8], key: [u8; 16], nonce: [u8; 8]) -
     out
 }
 
+// Full-block CFB: each block of keystream is the encryption of the previous
+// ciphertext block, starting from the IV. Like CTR, there's no padding.
+pub fn cfb_encrypt<T>(cipher: &T, pt: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8>
+    where T: BlockCipher {
+    cfb(cipher, pt, key, iv, false)
+}
+
+pub fn cfb_decrypt<T>(cipher: &T, ct: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8>
+    where T: BlockCipher {
+    cfb(cipher, ct, key, iv, true)
+}
+
+fn cfb<T>(cipher: &T, bytes: &[u8], key: &[u8], iv: &[u8], decrypt: bool) -> Vec<u8>
+    where T: BlockCipher {
+    let mut out = Vec::<u8>::new();
+    let mut prev = iv.to_vec();
+    for chunk in bytes.chunks(cipher.block_size()) {
+        let keystream = cipher.encrypt(&prev, key);
+        let res: Vec<u8> = zip(chunk.iter(), &keystream).map(|(i, j)| i ^ j).collect();
+        prev = if decrypt { chunk.to_vec() } else { res.clone() };
+        out.extend(&res);
+    }
+    out
+}
+
+// OFB: the keystream is the IV encrypted over and over. Encryption and
+// decryption are the same operation.
+pub fn ofb_encrypt<T>(cipher: &T, bytes: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8>
+    where T: BlockCipher {
+    let mut out = Vec::<u8>::new();
+    let mut keystream = iv.to_vec();
+    for chunk in bytes.chunks(cipher.block_size()) {
+        keystream = cipher.encrypt(&keystream, key);
+        out.extend(zip(chunk.iter(), &keystream).map(|(i, j)| i ^ j));
+    }
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EcbReport {
+    pub blocks: usize,
+    pub repeats: usize,
+    pub probability: f64
+}
+
+// ECB encrypts equal plaintext blocks to equal ciphertext blocks, so count
+// the blocks that repeat an earlier one. Other modes make repeats about as
+// likely as a collision between random blocks.
+//
+// The probability that the ciphertext is ECB weighs that against how often
+// we'd expect to see repeats under ECB, assuming any two plaintext blocks
+// match 1% of the time and ECB and the alternatives are equally likely to
+// begin with. So a long ECB ciphertext with no repeats is unlikely, but a
+// short one might just have an unremarkable plaintext.
+pub fn detect_ecb(ct: &[u8], block_size: usize) -> EcbReport {
+    let blocks: Vec<&[u8]> = ct.chunks(block_size)
+        .filter(|block| block.len() == block_size)
+        .collect();
+    let distinct: HashSet<&[u8]> = blocks.iter().cloned().collect();
+    let repeats = blocks.len() - distinct.len();
+
+    let pairs = (blocks.len() * blocks.len().saturating_sub(1) / 2) as f64;
+    let p_random = (pairs / 2f64.powi(8 * block_size as i32)).min(1.0);
+    let p_ecb = 1.0 - 0.99f64.powf(pairs);
+    let probability = if repeats > 0 {
+        p_ecb / (p_ecb + p_random)
+    } else {
+        (1.0 - p_ecb) / ((1.0 - p_ecb) + (1.0 - p_random))
+    };
+
+    EcbReport { blocks: blocks.len(), repeats: repeats, probability: probability }
+}
+
+// Rank ciphertexts by how likely they are to be ECB, best first, along with
+// their indices.
+pub fn rank_ecb<T>(cts: &[T], block_size: usize) -> Vec<(usize, EcbReport)>
+    where T: AsRef<[u8]> {
+    let mut ranked: Vec<(usize, EcbReport)> = cts.iter()
+        .map(|ct| detect_ecb(ct.as_ref(), block_size))
+        .enumerate()
+        .collect();
+    ranked.sort_by(|a, b| {
+        (b.1.probability, b.1.repeats).partial_cmp(&(a.1.probability, a.1.repeats)).unwrap()
+    });
+    ranked
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -147,4 +248,47 @@ mod tests {
             assert_eq!(pt[..], ct[..]);
         }
     }
+
+    #[test]
+    fn test_cfb_ofb() {
+        let key = b"YELLOW SUBMARINE";
+        let iv = [7; 16];
+        let plaintext = b"abcdefghijklmnopqrstuvwxyz0123456789!@#$%^&*()";
+        let enc = cfb_encrypt(AES128, plaintext, key, &iv);
+        assert_eq!(enc.len(), plaintext.len());
+        assert_eq!(cfb_decrypt(AES128, &enc, key, &iv), plaintext.to_vec());
+        let enc = ofb_encrypt(AES128, plaintext, key, &iv);
+        assert_eq!(ofb_encrypt(AES128, &enc, key, &iv), plaintext.to_vec());
+
+        // The first block of both is just the IV encrypted.
+        let block = AES128.encrypt(&iv, key);
+        let first: Vec<u8> = zip(&plaintext[..16], &block).map(|(a, b)| a ^ b).collect();
+        assert_eq!(cfb_encrypt(AES128, plaintext, key, &iv)[..16], first[..]);
+        assert_eq!(ofb_encrypt(AES128, plaintext, key, &iv)[..16], first[..]);
+    }
+
+    #[test]
+    fn test_detect_ecb() {
+        let key = b"YELLOW SUBMARINE";
+        let plaintext = [b'A'; 64];
+        let ecb = detect_ecb(&ecb_encrypt(AES128, &plaintext, key), 16);
+        assert_eq!((ecb.blocks, ecb.repeats), (5, 3));
+        assert!(ecb.probability > 0.99);
+
+        let cbc = detect_ecb(&cbc_encrypt(AES128, &plaintext, key, &[0; 16]), 16);
+        assert_eq!(cbc.repeats, 0);
+        assert!(cbc.probability < 0.5);
+
+        let short = detect_ecb(&ecb_encrypt(AES128, b"hi", key), 16);
+        assert!((short.probability - 0.5).abs() < 0.01);
+
+        let eight = detect_ecb(b"abcdefghabcdefgh", 8);
+        assert_eq!(eight.repeats, 1);
+
+        let ranked = rank_ecb(&[
+            cbc_encrypt(AES128, &plaintext, key, &[0; 16]),
+            ecb_encrypt(AES128, &plaintext, key)
+        ], 16);
+        assert_eq!(ranked[0].0, 1);
+    }
 }
@@ -0,0 +1,364 @@
+use keysize::*;
+use stats::*;
+use xor::transpose;
+
+use itertools::Itertools;
+use rand::Rng;
+use std::f32;
+
+// Pen-and-paper ciphers over the 26-letter alphabet. Letters keep their case
+// and everything else passes through untouched. Keys are given as letters or
+// numbers from 0 to 25.
+
+fn map_letter<F>(c: u8, f: F) -> u8 where F: Fn(u8) -> u8 {
+    if c.is_ascii_uppercase() {
+        b'A' + f(c - b'A')
+    } else if c.is_ascii_lowercase() {
+        b'a' + f(c - b'a')
+    } else {
+        c
+    }
+}
+
+// Just the letters of `s`, in uppercase.
+pub fn letters(s: &[u8]) -> Vec<u8> {
+    s.iter().filter_map(|&c| alph(c)).map(|x| b'A' + x).collect()
+}
+
+// Trigrams of the English corpus with everything but the letters stripped,
+// for scoring ciphers that only ever see letters.
+pub fn letter_trigrams() -> Ngrams {
+    Ngrams::from_corpus(&letters(ENGLISH_CORPUS), 3)
+}
+
+// A scorer can give NaN for text it makes no sense of; that counts as the
+// worst possible score rather than tripping up the sort.
+fn best_by_score<T, S>(candidates: Vec<(Vec<u8>, T)>, scorer: &S) -> (Vec<u8>, T)
+    where S: Scorer {
+    let mut scored: Vec<(f32, (Vec<u8>, T))> = candidates.into_iter()
+        .map(|candidate| {
+            let score = scorer.score(&candidate.0);
+            (if score.is_nan() { f32::INFINITY } else { score }, candidate)
+        })
+        .collect();
+    scored.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    scored.remove(0).1
+}
+
+pub fn caesar_encrypt(pt: &[u8], shift: u8) -> Vec<u8> {
+    pt.iter().map(|&c| map_letter(c, |x| (x + shift % 26) % 26)).collect()
+}
+
+pub fn caesar_decrypt(ct: &[u8], shift: u8) -> Vec<u8> {
+    caesar_encrypt(ct, 26 - shift % 26)
+}
+
+pub fn break_caesar<S>(ct: &[u8], scorer: &S) -> (Vec<u8>, u8) where S: Scorer {
+    let candidates = (0..26).map(|shift| (caesar_decrypt(ct, shift), shift)).collect();
+    best_by_score(candidates, scorer)
+}
+
+// The key only moves on when a letter is enciphered, so spaces and
+// punctuation don't throw it out of step.
+fn vigenere(text: &[u8], key: &[u8], decrypt: bool) -> Vec<u8> {
+    let shifts: Vec<u8> = key.iter().filter_map(|&c| alph(c))
+        .map(|x| if decrypt { (26 - x) % 26 } else { x })
+        .collect();
+    if shifts.is_empty() {
+        return text.to_vec();
+    }
+    let mut position = 0;
+    text.iter().map(|&c| {
+        if alph(c).is_none() {
+            return c;
+        }
+        let shift = shifts[position % shifts.len()];
+        position += 1;
+        map_letter(c, |x| (x + shift) % 26)
+    }).collect()
+}
+
+pub fn vigenere_encrypt(pt: &[u8], key: &[u8]) -> Vec<u8> {
+    vigenere(pt, key, false)
+}
+
+pub fn vigenere_decrypt(ct: &[u8], key: &[u8]) -> Vec<u8> {
+    vigenere(ct, key, true)
+}
+
+// Find the key length from the index of coincidence of the letters, then
+// break each column as a Caesar cipher. Returns the plaintext and the key.
+pub fn break_vigenere<S>(ct: &[u8], max_keylen: usize, scorer: &S) -> (Vec<u8>, Vec<u8>)
+    where S: Scorer {
+    let text = letters(ct);
+    let keylen = guess_keysize(&text, 1, max_keylen).unwrap_or(1);
+    let key: Vec<u8> = transpose(&text, keylen)
+        .iter()
+        .map(|column| b'A' + break_caesar(column, scorer).1)
+        .collect();
+    (vigenere_decrypt(ct, &key), key)
+}
+
+fn inverse_mod_26(a: u8) -> Option<u8> {
+    (1..26).find(|&x| (u32::from(a) * u32::from(x)) % 26 == 1)
+}
+
+// E(x) = ax + b (mod 26), which only has an inverse when a is coprime to 26.
+pub fn affine_encrypt(pt: &[u8], a: u8, b: u8) -> Result<Vec<u8>, String> {
+    inverse_mod_26(a % 26)
+        .ok_or_else(|| format!("Affine multiplier {} is not coprime to 26", a))?;
+    Ok(pt.iter()
+        .map(|&c| map_letter(c, |x| ((u32::from(a) * u32::from(x) + u32::from(b)) % 26) as u8))
+        .collect())
+}
+
+pub fn affine_decrypt(ct: &[u8], a: u8, b: u8) -> Result<Vec<u8>, String> {
+    let a_inv = inverse_mod_26(a % 26)
+        .ok_or_else(|| format!("Affine multiplier {} is not coprime to 26", a))?;
+    Ok(ct.iter()
+        .map(|&c| map_letter(c, |y| {
+            ((u32::from(a_inv) * (u32::from(y) + 26 - u32::from(b % 26))) % 26) as u8
+        }))
+        .collect())
+}
+
+// There are only 12 * 26 keys, so try them all. Returns the plaintext, a
+// and b.
+pub fn break_affine<S>(ct: &[u8], scorer: &S) -> (Vec<u8>, u8, u8) where S: Scorer {
+    let mut candidates = Vec::new();
+    for a in (1..26).filter(|&a| inverse_mod_26(a).is_some()) {
+        for b in 0..26 {
+            candidates.push((affine_decrypt(ct, a, b).unwrap(), (a, b)));
+        }
+    }
+    let (pt, (a, b)) = best_by_score(candidates, scorer);
+    (pt, a, b)
+}
+
+// A substitution key is the 26 letters that A to Z become, in order.
+fn check_substitution_key(key: &[u8]) -> Result<Vec<u8>, String> {
+    let key = letters(key);
+    let mut seen = [false; 26];
+    for &c in &key {
+        seen[(c - b'A') as usize] = true;
+    }
+    if key.len() != 26 || seen.iter().any(|&s| !s) {
+        return Err("Substitution key must be a permutation of the alphabet".to_string());
+    }
+    Ok(key)
+}
+
+pub fn substitution_encrypt(pt: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+    let key = check_substitution_key(key)?;
+    Ok(pt.iter().map(|&c| map_letter(c, |x| key[x as usize] - b'A')).collect())
+}
+
+pub fn substitution_decrypt(ct: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+    let key = check_substitution_key(key)?;
+    let mut inverse = [0; 26];
+    for (plain, &cipher) in key.iter().enumerate() {
+        inverse[(cipher - b'A') as usize] = plain as u8;
+    }
+    Ok(ct.iter().map(|&c| map_letter(c, |x| inverse[x as usize])).collect())
+}
+
+// Hill-climb on the decryption key: swap pairs of letters and keep any swap
+// that improves the score, until no swap helps. The first climb starts from
+// matching up letter frequencies; each restart scrambles a few letters of the
+// best key so far. The scorer only sees the uppercase letters of the text, so
+// `letter_trigrams` is a good choice. The restarts draw from `rng`, so a
+// seeded one gives the same answer every time. Returns the plaintext and the
+// key.
+pub fn break_substitution<S, R>(ct: &[u8], scorer: &S, restarts: usize, rng: &mut R)
+    -> (Vec<u8>, Vec<u8>) where S: Scorer, R: Rng {
+    let text = letters(ct);
+    let decrypt = |dec: &[u8]| -> Vec<u8> {
+        text.iter().map(|&c| dec[(c - b'A') as usize]).collect()
+    };
+
+    let mut by_freq: Vec<u8> = (b'A'..b'Z' + 1).collect();
+    by_freq.sort_by_key(|&c| -(text.iter().filter(|&&t| t == c).count() as i64));
+    let mut dec = [0u8; 26];
+    for (&cipher, &plain) in by_freq.iter().zip(b"ETAOINSHRDLCUMWFGYPBVKJXQZ") {
+        dec[(cipher - b'A') as usize] = plain;
+    }
+
+    let mut best = dec;
+    let mut best_score = scorer.score(&decrypt(&best));
+    for attempt in 0..restarts + 1 {
+        let mut current = best;
+        if attempt > 0 {
+            for _ in 0..4 {
+                current.swap(rng.gen_range(0, 26), rng.gen_range(0, 26));
+            }
+        }
+        let mut current_score = scorer.score(&decrypt(&current));
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for i in 0..26 {
+                for j in i + 1..26 {
+                    current.swap(i, j);
+                    let s = scorer.score(&decrypt(&current));
+                    if s < current_score {
+                        current_score = s;
+                        improved = true;
+                    } else {
+                        current.swap(i, j);
+                    }
+                }
+            }
+        }
+        if current_score < best_score {
+            best = current;
+            best_score = current_score;
+        }
+    }
+
+    let mut key = vec![0; 26];
+    for (cipher, &plain) in best.iter().enumerate() {
+        key[(plain - b'A') as usize] = b'A' + cipher as u8;
+    }
+    (substitution_decrypt(ct, &key).unwrap(), key)
+}
+
+// The order in which a keyword's columns are read off: alphabetical by
+// letter, with ties left to right. "ZEBRAS" gives [4, 2, 1, 3, 5, 0].
+pub fn columnar_order(keyword: &[u8]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..keyword.len()).collect();
+    order.sort_by_key(|&i| keyword[i].to_ascii_uppercase());
+    order
+}
+
+// Write the text in rows as wide as the key, then read it off a column at a
+// time in key order. The last row may be short; there's no padding.
+pub fn columnar_encrypt(pt: &[u8], order: &[usize]) -> Vec<u8> {
+    let width = order.len();
+    let mut out = Vec::with_capacity(pt.len());
+    for &col in order {
+        out.extend(pt.iter().skip(col).step(width));
+    }
+    out
+}
+
+pub fn columnar_decrypt(ct: &[u8], order: &[usize]) -> Vec<u8> {
+    let width = order.len();
+    if width == 0 {
+        return ct.to_vec();
+    }
+    let (full_rows, long_cols) = (ct.len() / width, ct.len() % width);
+    let mut columns = vec![&ct[..0]; width];
+    let mut pos = 0;
+    for &col in order {
+        let len = full_rows + if col < long_cols { 1 } else { 0 };
+        columns[col] = &ct[pos..pos + len];
+        pos += len;
+    }
+    (0..ct.len()).map(|i| columns[i % width][i / width]).collect()
+}
+
+// For every width up to `max_width`, hill-climb on the column order by
+// swapping pairs of columns, restarting from a few orders drawn from `rng`.
+// Returns the plaintext and the column order.
+pub fn break_columnar<S, R>(ct: &[u8], max_width: usize, scorer: &S, restarts: usize,
+                            rng: &mut R) -> (Vec<u8>, Vec<usize>) where S: Scorer, R: Rng {
+    let mut best = (f32::INFINITY, vec![0]);
+    for width in 2..max_width + 1 {
+        for attempt in 0..restarts + 1 {
+            let mut order: Vec<usize> = (0..width).collect();
+            if attempt > 0 {
+                rng.shuffle(&mut order);
+            }
+            let mut current_score = scorer.score(&columnar_decrypt(ct, &order));
+            let mut improved = true;
+            while improved {
+                improved = false;
+                for i in 0..width {
+                    for j in i + 1..width {
+                        order.swap(i, j);
+                        let s = scorer.score(&columnar_decrypt(ct, &order));
+                        if s < current_score {
+                            current_score = s;
+                            improved = true;
+                        } else {
+                            order.swap(i, j);
+                        }
+                    }
+                }
+            }
+            if current_score < best.0 {
+                best = (current_score, order);
+            }
+        }
+    }
+    (columnar_decrypt(ct, &best.1), best.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::zip;
+    use rand::{SeedableRng, XorShiftRng};
+
+    fn seeded() -> XorShiftRng {
+        XorShiftRng::from_seed([1, 2, 3, 4])
+    }
+
+    fn sample() -> &'static [u8] {
+        &ENGLISH_CORPUS[..1200]
+    }
+
+    #[test]
+    fn test_caesar() {
+        assert_eq!(caesar_encrypt(b"Hello, World!", 3), b"Khoor, Zruog!".to_vec());
+        assert_eq!(caesar_decrypt(b"Khoor, Zruog!", 3), b"Hello, World!".to_vec());
+        let (pt, shift) = break_caesar(&caesar_encrypt(sample(), 17), &score);
+        assert_eq!((&pt[..], shift), (sample(), 17));
+    }
+
+    #[test]
+    fn test_vigenere() {
+        assert_eq!(vigenere_encrypt(b"ATTACK AT DAWN", b"LEMON"), b"LXFOPV EF RNHR".to_vec());
+        assert_eq!(vigenere_decrypt(b"LXFOPV EF RNHR", b"lemon"), b"ATTACK AT DAWN".to_vec());
+        let ct = vigenere_encrypt(sample(), b"VANILLA");
+        let (pt, key) = break_vigenere(&ct, 20, &score);
+        assert_eq!((&pt[..], &key[..]), (sample(), &b"VANILLA"[..]));
+    }
+
+    #[test]
+    fn test_affine() {
+        assert_eq!(affine_encrypt(b"AFFINE cipher", 5, 8).unwrap(), b"IHHWVC swfrcp".to_vec());
+        assert!(affine_encrypt(b"AFFINE", 13, 8).is_err());
+        let ct = affine_encrypt(sample(), 7, 22).unwrap();
+        let (pt, a, b) = break_affine(&ct, &score);
+        assert_eq!((&pt[..], a, b), (sample(), 7, 22));
+    }
+
+    #[test]
+    fn test_substitution() {
+        let key = b"QWERTYUIOPASDFGHJKLZXCVBNM";
+        let ct = substitution_encrypt(b"Hello there", key).unwrap();
+        assert_eq!(ct, b"Itssg zitkt".to_vec());
+        assert_eq!(substitution_decrypt(&ct, key).unwrap(), b"Hello there".to_vec());
+        assert!(substitution_encrypt(b"x", b"AABCDEFGHIJKLMNOPQRSTUVWXY").is_err());
+
+        let ct = substitution_encrypt(sample(), key).unwrap();
+        let (pt, _) = break_substitution(&ct, &letter_trigrams(), 5, &mut seeded());
+        let correct = zip(letters(&pt), letters(sample())).filter(|&(a, b)| a == b).count();
+        assert!(correct as f32 > 0.95 * letters(sample()).len() as f32);
+    }
+
+    #[test]
+    fn test_columnar() {
+        let order = columnar_order(b"ZEBRAS");
+        assert_eq!(order, vec![4, 2, 1, 3, 5, 0]);
+        let pt = b"WE ARE DISCOVERED FLEE AT ONCE";
+        let ct = columnar_encrypt(pt, &order);
+        assert_eq!(columnar_decrypt(&ct, &order), pt.to_vec());
+
+        let pt = &sample()[..400];
+        let ct = columnar_encrypt(pt, &columnar_order(b"CIPHER"));
+        let (found, _) = break_columnar(&ct, 8, &Ngrams::english_trigrams(), 3, &mut seeded());
+        assert_eq!(&found[..], pt);
+    }
+}
@@ -0,0 +1,58 @@
+use std::cell::Cell;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Anything that can tell the time and wait. Challenges that depend on the
+// wall clock take one of these so that tests can skip the waiting.
+pub trait Clock {
+    fn now(&self) -> u32;
+    fn sleep(&self, secs: u64);
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u32 {
+        SystemTime::now().duration_since(UNIX_EPOCH)
+            .expect("Time went backwards").as_secs() as u32
+    }
+
+    fn sleep(&self, secs: u64) {
+        sleep(Duration::from_secs(secs));
+    }
+}
+
+// A clock that only moves when somebody sleeps on it.
+#[derive(Debug)]
+pub struct FakeClock {
+    time: Cell<u32>
+}
+
+impl FakeClock {
+    pub fn new(start: u32) -> Self {
+        FakeClock { time: Cell::new(start) }
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> u32 {
+        self.time.get()
+    }
+
+    fn sleep(&self, secs: u64) {
+        self.time.set(self.time.get() + secs as u32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_clock() {
+        let clock = FakeClock::new(1000);
+        clock.sleep(40);
+        assert_eq!(clock.now(), 1040);
+    }
+}
@@ -1,52 +1,135 @@
 use std::char;
-use std::cmp::min;
-use std::str::from_utf8;
 
 pub static HEX_TABLE: &[u8] = b"0123456789abcdef";
 pub static B64_TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ\
     abcdefghijklmnopqrstuvwxyz0123456789+/";
 
+pub static HEX_UPPER_TABLE: &[u8] = b"0123456789ABCDEF";
+
 pub fn base16_encode(data: &[u8]) -> Vec<u8> {
+    base16_encode_with(data, HEX_TABLE)
+}
+
+pub fn base16_encode_upper(data: &[u8]) -> Vec<u8> {
+    base16_encode_with(data, HEX_UPPER_TABLE)
+}
+
+fn base16_encode_with(data: &[u8], table: &[u8]) -> Vec<u8> {
     let mut encoded = Vec::new();
     for byte in data {
         let up = byte / 16;
         let down = byte % 16;
-        let out = [HEX_TABLE[up as usize], HEX_TABLE[down as usize]];
+        let out = [table[up as usize], table[down as usize]];
         encoded.extend(out.iter().cloned());
     }
     encoded
 
 }
 
+// Accepts either case, an optional leading `0x`, and whitespace or colons
+// anywhere between digits, so `DE:AD:be:ef` and `0xdeadbeef` both work.
 pub fn base16_decode(contents: &[u8]) -> Result<Vec<u8>, String> {
-    let mut decoded = Vec::<u8>::new();
-    for byte in contents.chunks(2) {
-        for hex_digit in byte {
-            if !b"0123456789abcdef".contains(hex_digit) {
-                return Err(format!(
-                    "Input contained invalid base16 character {}",
-                    char::from(*hex_digit)));
-            }
+    let mut contents = contents;
+    if contents.starts_with(b"0x") || contents.starts_with(b"0X") {
+        contents = &contents[2..];
+    }
+    let mut nibbles = Vec::<u8>::with_capacity(contents.len());
+    for (pos, &hex_digit) in contents.iter().enumerate() {
+        if hex_digit == b':' || (hex_digit as char).is_whitespace() {
+            continue;
         }
-        let s = from_utf8(byte).unwrap();
-        if let Ok(n) = u8::from_str_radix(s, 16) {
-            decoded.push(n);
+        match (hex_digit as char).to_digit(16) {
+            Some(n) => nibbles.push(n as u8),
+            None => return Err(format!(
+                "Input contained invalid base16 character {} at position {}",
+                char::from(hex_digit), pos))
         }
     }
-    Ok(decoded)
+    if nibbles.len() % 2 != 0 {
+        return Err(format!(
+            "Input contained an odd number ({}) of base16 digits",
+            nibbles.len()));
+    }
+    Ok(nibbles.chunks(2).map(|pair| pair[0] * 16 + pair[1]).collect())
 }
 
-pub fn base16_decode_filter(contents: &[u8]) -> Vec<u8> {
+// Drops everything that isn't a hex digit (after any leading `0x`) and
+// decodes the rest, which can still fail if an odd number of digits is left.
+pub fn base16_decode_filter(contents: &[u8]) -> Result<Vec<u8>, String> {
+    let mut contents = contents;
+    if contents.starts_with(b"0x") || contents.starts_with(b"0X") {
+        contents = &contents[2..];
+    }
     base16_decode(
         &contents.iter().cloned()
-            .filter(|x| HEX_TABLE.contains(x))
-            .collect::<Vec<u8>>()[..]).unwrap()
+            .filter(|x| x.is_ascii_hexdigit())
+            .collect::<Vec<u8>>()[..])
 }
 
+// Lay out bytes like `hexdump -C`: offset, 16 bytes of hex split into two
+// groups of 8, then the printable ASCII, with the total length at the end.
+pub fn hexdump(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (line_ix, line) in data.chunks(16).enumerate() {
+        out.push_str(&format!("{:08x}  ", line_ix * 16));
+        for i in 0..16 {
+            match line.get(i) {
+                Some(byte) => out.push_str(&format!("{:02x} ", byte)),
+                None       => out.push_str("   ")
+            }
+            if i == 7 {
+                out.push(' ');
+            }
+        }
+        out.push_str(" |");
+        for &byte in line {
+            out.push(if byte >= 0x20 && byte < 0x7f { byte as char } else { '.' });
+        }
+        out.push_str("|\n");
+    }
+    out.push_str(&format!("{:08x}\n", data.len()));
+    out
+}
+
+pub static B64_URL_TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ\
+    abcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// How to read and write base64. `strict` decoding rejects anything that the
+// matching encoder wouldn't have produced: stray whitespace, missing or extra
+// padding, and nonzero leftover bits in the last character. Lenient decoding
+// skips whitespace and doesn't care whether the padding is there.
+#[derive(Debug, Copy, Clone)]
+pub struct Base64Config {
+    pub alphabet: &'static [u8],
+    pub pad: bool,
+    pub strict: bool,
+    pub line_wrap: Option<usize>
+}
+
+pub const B64_STANDARD: Base64Config = Base64Config {
+    alphabet: B64_TABLE, pad: true, strict: true, line_wrap: None
+};
+pub const B64_URL_SAFE: Base64Config = Base64Config {
+    alphabet: B64_URL_TABLE, pad: true, strict: true, line_wrap: None
+};
+pub const B64_URL_SAFE_NO_PAD: Base64Config = Base64Config {
+    alphabet: B64_URL_TABLE, pad: false, strict: true, line_wrap: None
+};
+pub const B64_LENIENT: Base64Config = Base64Config {
+    alphabet: B64_TABLE, pad: true, strict: false, line_wrap: None
+};
+// RFC 2045 wants lines of at most 76 characters, separated by CRLF.
+pub const B64_MIME: Base64Config = Base64Config {
+    alphabet: B64_TABLE, pad: true, strict: false, line_wrap: Some(76)
+};
+
 pub fn base64_encode(data: &[u8]) -> Vec<u8> {
+    base64_encode_config(data, B64_STANDARD)
+}
+
+pub fn base64_encode_config(data: &[u8], config: Base64Config) -> Vec<u8> {
     let mut encoded = Vec::new();
     for triplet in data.chunks(3) {
-        let mut out = [61; 4];
         let mut triplet_buf = [0; 3];
         triplet_buf[..triplet.len()].copy_from_slice(&triplet[..]);
         let bits =
@@ -54,41 +137,89 @@ pub fn base64_encode(data: &[u8]) -> Vec<u8> {
             triplet_buf[1] as usize * 256 +
             triplet_buf[2] as usize;
         for i in 0..triplet.len() + 1 {
-            out[i] = B64_TABLE[(bits >> (6 * (3 - i))) % 64];
+            encoded.push(config.alphabet[(bits >> (6 * (3 - i))) % 64]);
+        }
+        if config.pad {
+            for _ in triplet.len()..3 {
+                encoded.push(b'=');
+            }
         }
-        encoded.extend(out.iter().cloned());
     }
-    encoded
+    match config.line_wrap {
+        Some(width) if width > 0 => wrap_lines(&encoded, width),
+        _ => encoded
+    }
+}
+
+fn wrap_lines(data: &[u8], width: usize) -> Vec<u8> {
+    let mut wrapped = Vec::with_capacity(data.len() + 2 * (data.len() / width));
+    for (i, line) in data.chunks(width).enumerate() {
+        if i > 0 {
+            wrapped.extend_from_slice(b"\r\n");
+        }
+        wrapped.extend_from_slice(line);
+    }
+    wrapped
 }
 
 pub fn base64_decode(data: &[u8]) -> Result<Vec<u8>, String> {
-    let mut decoded = Vec::<u8>::new();
-    for quartet in data.chunks(4) {
-        let mut indices = [0; 4];
-        let mut non_pad_bytes = 4;
+    base64_decode_config(data, B64_STANDARD)
+}
+
+pub fn base64_decode_config(data: &[u8], config: Base64Config)
+    -> Result<Vec<u8>, String> {
+    let mut indices = Vec::<u8>::with_capacity(data.len());
+    let mut pad_bytes = 0;
+    for (pos, &byte) in data.iter().enumerate() {
+        let is_newline = byte == b'\r' || byte == b'\n';
+        if (!config.strict && (byte as char).is_whitespace()) ||
+            (config.line_wrap.is_some() && is_newline) {
+            continue;
+        }
+        if byte == b'=' {
+            pad_bytes += 1;
+        } else if pad_bytes > 0 {
+            return Err(format!(
+                "Base64 input continued after padding at position {}", pos));
+        } else if let Some(ix) = config.alphabet.iter().position(|y| *y == byte) {
+            indices.push(ix as u8);
+        } else {
+            return Err(format!(
+                "Input contained invalid base64 character {} at position {}",
+                char::from(byte), pos));
+        }
+    }
+
+    let missing = (4 - indices.len() % 4) % 4;
+    if missing == 3 {
+        return Err(format!(
+            "Base64 input has a dangling character ({} characters of data)",
+            indices.len()));
+    }
+    if pad_bytes > 2 || (pad_bytes > 0 && pad_bytes != missing) {
+        return Err(format!(
+            "Base64 input has {} padding characters where {} were expected",
+            pad_bytes, missing));
+    }
+    if config.strict && config.pad && pad_bytes != missing {
+        return Err("Base64 input is missing its padding".to_string());
+    }
+    if config.strict && !config.pad && pad_bytes > 0 {
+        return Err("Base64 input is padded but padding is disabled".to_string());
+    }
+
+    let mut decoded = Vec::<u8>::with_capacity(indices.len() * 3 / 4);
+    for quartet in indices.chunks(4) {
+        let mut bits: u32 = 0;
         for i in 0..4 {
-            let byte = quartet[i];
-            if let Some(ix) = B64_TABLE.iter().position(|y| *y == byte) {
-                indices[i] = ix as u8;
-            } else if byte == 61 {
-                non_pad_bytes = min(non_pad_bytes, i);
-            } else {
-                return Err(format!(
-                    "Input contained invalid base64 character {}",
-                    char::from(byte)));
-            }
+            bits = (bits << 6) + u32::from(*quartet.get(i).unwrap_or(&0));
         }
-        let v = [
-            (indices[0] << 2) + (indices[1] >> 4),
-            (indices[1] << 4) + (indices[2] >> 2),
-            (indices[2] << 6) + (indices[3]     )
-        ];
-        match non_pad_bytes {
-            4 => decoded.extend(&v[..3]),
-            3 => decoded.extend(&v[..2]),
-            2 => decoded.extend(&v[..1]),
-            _ => unreachable!()
+        let v = [(bits >> 16) as u8, (bits >> 8) as u8, bits as u8];
+        let out_len = quartet.len() - 1;
+        if config.strict && v[out_len..].iter().any(|&b| b != 0) {
+            return Err("Base64 input has nonzero trailing bits".to_string());
         }
+        decoded.extend(&v[..out_len]);
     }
     Ok(decoded)
 }
@@ -100,6 +231,343 @@ pub fn base64_decode_filter(contents: &[u8]) -> Vec<u8> {
             .collect::<Vec<u8>>()[..]).unwrap()
 }
 
+pub static B32_TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+pub static B32_HEX_TABLE: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUV";
+
+pub fn base32_encode(data: &[u8], alphabet: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for quintet in data.chunks(5) {
+        let mut quintet_buf = [0; 5];
+        quintet_buf[..quintet.len()].copy_from_slice(quintet);
+        let bits = quintet_buf.iter()
+            .fold(0u64, |acc, &byte| (acc << 8) + u64::from(byte));
+        // Each input byte touches a new output character, plus one for the
+        // leftover bits.
+        let out_chars = (quintet.len() * 8 + 4) / 5;
+        for i in 0..8 {
+            if i < out_chars {
+                encoded.push(alphabet[((bits >> (5 * (7 - i))) % 32) as usize]);
+            } else {
+                encoded.push(b'=');
+            }
+        }
+    }
+    encoded
+}
+
+// Lowercase input is accepted, since plenty of tools emit it.
+pub fn base32_decode(data: &[u8], alphabet: &[u8]) -> Result<Vec<u8>, String> {
+    let unpadded_len = data.iter().rposition(|&b| b != b'=').map_or(0, |i| i + 1);
+    if unpadded_len < data.len() && data.len() % 8 != 0 {
+        return Err(format!(
+            "Padded base32 input has length {}, which is not a multiple of 8",
+            data.len()));
+    }
+    let mut indices = Vec::<u8>::with_capacity(unpadded_len);
+    for (pos, &byte) in data[..unpadded_len].iter().enumerate() {
+        match alphabet.iter().position(|&y| y == byte.to_ascii_uppercase()) {
+            Some(ix) => indices.push(ix as u8),
+            None => return Err(format!(
+                "Input contained invalid base32 character {} at position {}",
+                char::from(byte), pos))
+        }
+    }
+    match indices.len() % 8 {
+        1 | 3 | 6 => return Err(format!(
+            "Base32 input has an impossible length of {} characters",
+            indices.len())),
+        _ => {}
+    }
+
+    let mut decoded = Vec::with_capacity(indices.len() * 5 / 8);
+    for octet in indices.chunks(8) {
+        let mut bits: u64 = 0;
+        for i in 0..8 {
+            bits = (bits << 5) + u64::from(*octet.get(i).unwrap_or(&0));
+        }
+        for i in 0..octet.len() * 5 / 8 {
+            decoded.push((bits >> (8 * (4 - i))) as u8);
+        }
+    }
+    Ok(decoded)
+}
+
+// The Bitcoin alphabet, which leaves out 0, O, I and l.
+pub static B58_TABLE: &[u8] =
+    b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+// Base58 treats the whole input as one big-endian number, so this is
+// quadratic in the input length. Leading zero bytes become leading '1's.
+pub fn base58_encode(data: &[u8]) -> Vec<u8> {
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+    // Little-endian base 58 digits.
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in &data[zeros..] {
+        let mut carry = u32::from(byte);
+        for digit in &mut digits {
+            carry += u32::from(*digit) * 256;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut encoded = vec![B58_TABLE[0]; zeros];
+    encoded.extend(digits.iter().rev().map(|&d| B58_TABLE[d as usize]));
+    encoded
+}
+
+pub fn base58_decode(data: &[u8]) -> Result<Vec<u8>, String> {
+    let zeros = data.iter().take_while(|&&b| b == B58_TABLE[0]).count();
+    // Little-endian base 256 digits.
+    let mut bytes: Vec<u8> = Vec::new();
+    for (pos, &ch) in data.iter().enumerate().skip(zeros) {
+        let mut carry = match B58_TABLE.iter().position(|&y| y == ch) {
+            Some(ix) => ix as u32,
+            None => return Err(format!(
+                "Input contained invalid base58 character {} at position {}",
+                char::from(ch), pos))
+        };
+        for byte in &mut bytes {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let mut decoded = vec![0; zeros];
+    decoded.extend(bytes.iter().rev());
+    Ok(decoded)
+}
+
+// Adobe's Ascii85 uses the 85 characters starting at '!'. Z85 is ZeroMQ's
+// variant, with an alphabet that's safe to put in source code.
+pub static A85_TABLE: &[u8] = b"!\"#$%&'()*+,-./0123456789:;<=>?@\
+    ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstu";
+pub static Z85_TABLE: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz\
+    ABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+// Every 4 bytes become 5 characters. A short final group is zero-padded,
+// encoded, and then cut down to one more character than it had bytes.
+// If `zero_shorthand` is set, a whole group of zeros is written as `z`.
+fn base85_encode(data: &[u8], alphabet: &[u8], zero_shorthand: bool) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for group in data.chunks(4) {
+        let mut group_buf = [0; 4];
+        group_buf[..group.len()].copy_from_slice(group);
+        let mut value = group_buf.iter()
+            .fold(0u32, |acc, &byte| (acc << 8) + u32::from(byte));
+        if zero_shorthand && group.len() == 4 && value == 0 {
+            encoded.push(b'z');
+            continue;
+        }
+        let mut out = [0; 5];
+        for i in (0..5).rev() {
+            out[i] = alphabet[(value % 85) as usize];
+            value /= 85;
+        }
+        encoded.extend_from_slice(&out[..group.len() + 1]);
+    }
+    encoded
+}
+
+fn base85_decode(data: &[u8], alphabet: &[u8], zero_shorthand: bool)
+    -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::new();
+    let mut group = Vec::with_capacity(5);
+    for (pos, &ch) in data.iter().enumerate() {
+        if (ch as char).is_whitespace() {
+            continue;
+        }
+        if zero_shorthand && ch == b'z' {
+            if !group.is_empty() {
+                return Err(format!(
+                    "Ascii85 input has 'z' inside a group at position {}", pos));
+            }
+            decoded.extend_from_slice(&[0; 4]);
+            continue;
+        }
+        match alphabet.iter().position(|&y| y == ch) {
+            Some(ix) => group.push(ix as u64),
+            None => return Err(format!(
+                "Input contained invalid base85 character {} at position {}",
+                char::from(ch), pos))
+        }
+        if group.len() == 5 {
+            decoded.extend_from_slice(&base85_group(&group)?);
+            group.clear();
+        }
+    }
+    match group.len() {
+        0 => {},
+        1 => return Err("Base85 input ends with a dangling character".to_string()),
+        n => {
+            // Pad with the largest digit so that truncation rounds correctly.
+            group.resize(5, 84);
+            decoded.extend_from_slice(&base85_group(&group)?[..n - 1]);
+        }
+    }
+    Ok(decoded)
+}
+
+fn base85_group(group: &[u64]) -> Result<[u8; 4], String> {
+    let value = group.iter().fold(0u64, |acc, &digit| acc * 85 + digit);
+    if value > u64::from(u32::max_value()) {
+        return Err("Base85 group is larger than 32 bits".to_string());
+    }
+    Ok([(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
+
+pub fn ascii85_encode(data: &[u8]) -> Vec<u8> {
+    base85_encode(data, A85_TABLE, true)
+}
+
+// The `<~` and `~>` delimiters are optional.
+pub fn ascii85_decode(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut data = data;
+    if data.starts_with(b"<~") {
+        data = &data[2..];
+    }
+    if data.ends_with(b"~>") {
+        data = &data[..data.len() - 2];
+    }
+    base85_decode(data, A85_TABLE, true)
+}
+
+// The Z85 spec only covers inputs that are a multiple of 4 bytes long;
+// anything else gets the same treatment as Ascii85's short final group.
+pub fn z85_encode(data: &[u8]) -> Vec<u8> {
+    base85_encode(data, Z85_TABLE, false)
+}
+
+pub fn z85_decode(data: &[u8]) -> Result<Vec<u8>, String> {
+    base85_decode(data, Z85_TABLE, false)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Encoding {
+    Hex,
+    Base64,
+    Base64Url,
+    Base32,
+    Pem,
+    Raw
+}
+
+fn is_text(byte: u8) -> bool {
+    (byte >= 0x20 && byte < 0x7f) || byte == b'\t' || byte == b'\r' || byte == b'\n'
+}
+
+fn in_any(byte: u8, tables: &[&[u8]]) -> bool {
+    tables.iter().any(|table| table.contains(&byte))
+}
+
+// Guess how a buffer is encoded, most restrictive alphabet first, since any
+// hex string is also valid base64. The confidence is the chance that the
+// input isn't just a coincidence: a string of n base64 characters has a
+// (22/65)^n chance of happening to look like hex, so short inputs don't
+// count for much. Printable input that doesn't decode as anything is called
+// raw with a confidence of 0.5, since it's probably plaintext.
+pub fn detect_encoding(data: &[u8]) -> (Encoding, f32) {
+    if data.is_empty() {
+        return (Encoding::Raw, 0.0);
+    }
+    if !data.iter().all(|&b| is_text(b)) {
+        return (Encoding::Raw, 1.0);
+    }
+    let has = |needle: &[u8]| data.windows(needle.len()).any(|w| w == needle);
+    if has(b"-----BEGIN ") && has(b"-----END ") &&
+        decode_as(data, Encoding::Pem).is_ok() {
+        return (Encoding::Pem, 1.0);
+    }
+
+    let symbols: Vec<u8> = data.iter().cloned()
+        .filter(|&b| !(b as char).is_whitespace())
+        .collect();
+    // Hex can also have a leading `0x` and colons, as base16_decode allows.
+    let hex_symbols: Vec<u8> = {
+        let digits = if symbols.starts_with(b"0x") || symbols.starts_with(b"0X") {
+            &symbols[2..]
+        } else {
+            &symbols[..]
+        };
+        digits.iter().cloned().filter(|&b| b != b':').collect()
+    };
+    let candidates: &[(Encoding, &[&[u8]], f32)] = &[
+        (Encoding::Hex,       &[HEX_TABLE, HEX_UPPER_TABLE], 22.0 / 65.0),
+        (Encoding::Base32,    &[B32_TABLE, b"="],            33.0 / 65.0),
+        (Encoding::Base64,    &[B64_TABLE, b"="],            65.0 / 95.0),
+        (Encoding::Base64Url, &[B64_URL_TABLE, b"="],        65.0 / 95.0)
+    ];
+    for &(encoding, tables, coincidence) in candidates {
+        let symbols = if encoding == Encoding::Hex { &hex_symbols } else { &symbols };
+        if symbols.iter().all(|&b| in_any(b, tables)) &&
+            decode_as(data, encoding).is_ok() {
+            return (encoding, 1.0 - coincidence.powi(symbols.len() as i32));
+        }
+    }
+    (Encoding::Raw, 0.5)
+}
+
+// Whitespace is ignored throughout. Base64 that has padding partway through
+// is treated as one record per line, like `data/17.txt`, and the decoded
+// lines are concatenated.
+pub fn decode_as(data: &[u8], encoding: Encoding) -> Result<Vec<u8>, String> {
+    let url_lenient = Base64Config { alphabet: B64_URL_TABLE, ..B64_LENIENT };
+    let compact = || data.iter().cloned()
+        .filter(|&b| !(b as char).is_whitespace())
+        .collect::<Vec<u8>>();
+    match encoding {
+        Encoding::Hex       => base16_decode(data),
+        Encoding::Base32    => base32_decode(&compact(), B32_TABLE),
+        Encoding::Base64    => base64_decode_lines(data, B64_LENIENT),
+        Encoding::Base64Url => base64_decode_lines(data, url_lenient),
+        Encoding::Pem       => pem_decode(data),
+        Encoding::Raw       => Ok(data.to_vec())
+    }
+}
+
+fn base64_decode_lines(data: &[u8], config: Base64Config) -> Result<Vec<u8>, String> {
+    base64_decode_config(data, config).or_else(|err| {
+        let mut decoded = Vec::new();
+        for line in data.split(|&b| b == b'\n') {
+            match base64_decode_config(line, config) {
+                Ok(bytes) => decoded.extend(bytes),
+                Err(_)    => return Err(err.clone())
+            }
+        }
+        Ok(decoded)
+    })
+}
+
+// Decode the body of the first PEM block, skipping any RFC 1421 headers.
+fn pem_decode(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    let mut inside = false;
+    for line in data.split(|&b| b == b'\n') {
+        if line.starts_with(b"-----BEGIN ") {
+            inside = true;
+        } else if line.starts_with(b"-----END ") {
+            if inside {
+                return base64_decode_config(&body, B64_LENIENT);
+            }
+        } else if inside && !line.contains(&b':') {
+            body.extend_from_slice(line);
+        }
+    }
+    Err("PEM input has no complete BEGIN/END block".to_string())
+}
+
+pub fn decode_detected(data: &[u8]) -> Result<(Encoding, f32, Vec<u8>), String> {
+    let (encoding, confidence) = detect_encoding(data);
+    decode_as(data, encoding).map(|decoded| (encoding, confidence, decoded))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -116,4 +584,155 @@ mod tests {
         identity(b"Ringo mogire beam!!");
         identity(b"Ringo mogire beam!!!");
     }
+
+    #[test]
+    fn test_base64_url_safe() {
+        let data = [0xfb, 0xff, 0xbf, 0x3e, 0x00];
+        assert_eq!(base64_encode(&data), b"+/+/PgA=".to_vec());
+        assert_eq!(base64_encode_config(&data, B64_URL_SAFE), b"-_-_PgA=".to_vec());
+        assert_eq!(base64_encode_config(&data, B64_URL_SAFE_NO_PAD), b"-_-_PgA".to_vec());
+        assert_eq!(base64_decode_config(b"-_-_PgA", B64_URL_SAFE_NO_PAD).unwrap(), data);
+        assert!(base64_decode_config(b"-_-_PgA=", B64_URL_SAFE_NO_PAD).is_err());
+        assert!(base64_decode(b"-_-_PgA=").is_err());
+    }
+
+    #[test]
+    fn test_base64_malformed() {
+        assert!(base64_decode(b"A===").is_err());
+        assert!(base64_decode(b"QUI").is_err());
+        assert!(base64_decode(b"QUJ=").is_err());
+        assert!(base64_decode(b"QU=I").is_err());
+        assert!(base64_decode(b"QUI=\n").is_err());
+        assert!(base64_decode_config(b"A", B64_LENIENT).is_err());
+        assert_eq!(base64_decode(b"QUI=").unwrap(), b"AB");
+        assert_eq!(base64_decode_config(b"QUI", B64_LENIENT).unwrap(), b"AB");
+        assert_eq!(base64_decode_config(b" QU\tI=\n", B64_LENIENT).unwrap(), b"AB");
+    }
+
+    #[test]
+    fn test_base64_mime() {
+        let data = vec![0x42; 200];
+        let enc = base64_encode_config(&data, B64_MIME);
+        let lines: Vec<&[u8]> = enc.split(|&b| b == b'\n').collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].len(), 77);
+        assert_eq!(lines[0][76], b'\r');
+        assert_eq!(base64_decode_config(&enc, B64_MIME).unwrap(), data);
+    }
+
+    #[test]
+    fn test_base32() {
+        let vectors: &[(&[u8], &[u8], &[u8])] = &[
+            (b"",       b"",                 b""),
+            (b"f",      b"MY======",         b"CO======"),
+            (b"fo",     b"MZXQ====",         b"CPNG===="),
+            (b"foo",    b"MZXW6===",         b"CPNMU==="),
+            (b"foob",   b"MZXW6YQ=",         b"CPNMUOG="),
+            (b"fooba",  b"MZXW6YTB",         b"CPNMUOJ1"),
+            (b"foobar", b"MZXW6YTBOI======", b"CPNMUOJ1E8======")
+        ];
+        for &(plain, std, hex) in vectors {
+            assert_eq!(base32_encode(plain, B32_TABLE)[..], std[..]);
+            assert_eq!(base32_encode(plain, B32_HEX_TABLE)[..], hex[..]);
+            assert_eq!(base32_decode(std, B32_TABLE).unwrap()[..], plain[..]);
+            assert_eq!(base32_decode(hex, B32_HEX_TABLE).unwrap()[..], plain[..]);
+        }
+        assert_eq!(base32_decode(b"mzxw6ytboi", B32_TABLE).unwrap(), b"foobar");
+        assert!(base32_decode(b"MZXW6YQ", B32_HEX_TABLE).is_err());
+        assert!(base32_decode(b"MZX=====", B32_TABLE).is_err());
+        assert!(base32_decode(b"MZXW6==", B32_TABLE).is_err());
+    }
+
+    #[test]
+    fn test_base58() {
+        assert_eq!(base58_encode(b"Hello World!"), b"2NEpo7TZRRrLZSi2U".to_vec());
+        assert_eq!(
+            base58_encode(b"The quick brown fox jumps over the lazy dog."),
+            b"USm3fpXnKG5EUBx2ndxBDMPVciP5hGey2Jh4NDv6gmeo1LkMeiKrLJUUBk6Z".to_vec());
+        let leading_zeros = [0x00, 0x00, 0x28, 0x7f, 0xb4, 0xcd];
+        assert_eq!(base58_encode(&leading_zeros), b"11233QC4".to_vec());
+        assert_eq!(base58_decode(b"11233QC4").unwrap(), leading_zeros);
+        assert_eq!(base58_decode(b"2NEpo7TZRRrLZSi2U").unwrap(), b"Hello World!");
+        assert!(base58_decode(b"0OIl").is_err());
+    }
+
+    #[test]
+    fn test_base85() {
+        assert_eq!(ascii85_encode(b"Man "), b"9jqo^".to_vec());
+        assert_eq!(ascii85_encode(b"sure."), b"F*2M7/c".to_vec());
+        assert_eq!(ascii85_encode(&[0, 0, 0, 0, 1]), b"z!<".to_vec());
+        assert_eq!(ascii85_decode(b"<~F*2M7/c~>").unwrap(), b"sure.");
+        assert_eq!(ascii85_decode(b"z!<").unwrap(), [0, 0, 0, 0, 1]);
+        assert!(ascii85_decode(b"F*2M7/").is_err());
+        assert!(ascii85_decode(b"s8W-\"").is_err());
+
+        let hello = [0x86, 0x4f, 0xd2, 0x6f, 0xb5, 0x59, 0xf7, 0x5b];
+        assert_eq!(z85_encode(&hello), b"HelloWorld".to_vec());
+        assert_eq!(z85_decode(b"HelloWorld").unwrap(), hello);
+
+        for len in 0..12 {
+            let data: Vec<u8> = (0..len).map(|i| (i * 37) as u8).collect();
+            assert_eq!(ascii85_decode(&ascii85_encode(&data)).unwrap(), data);
+            assert_eq!(z85_decode(&z85_encode(&data)).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_base16() {
+        assert_eq!(base16_encode(&[0xde, 0xad, 0x0f]), b"dead0f".to_vec());
+        assert_eq!(base16_encode_upper(&[0xde, 0xad, 0x0f]), b"DEAD0F".to_vec());
+        assert_eq!(base16_decode(b"DEad0f").unwrap(), [0xde, 0xad, 0x0f]);
+        assert_eq!(base16_decode(b"0xdead0f").unwrap(), [0xde, 0xad, 0x0f]);
+        assert_eq!(base16_decode(b"de:ad:0f").unwrap(), [0xde, 0xad, 0x0f]);
+        assert_eq!(base16_decode(b"de ad\n0f").unwrap(), [0xde, 0xad, 0x0f]);
+        assert!(base16_decode(b"dead0").is_err());
+        assert!(base16_decode(b"deag").is_err());
+        assert_eq!(base16_decode_filter(b"DE-AD").unwrap(), [0xde, 0xad]);
+        assert_eq!(base16_decode_filter(b"0xDE-AD").unwrap(), [0xde, 0xad]);
+        assert!(base16_decode_filter(b"DE-A").is_err());
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let dump = hexdump(b"YELLOW SUBMARINE\x00\xff!");
+        assert_eq!(dump,
+            "00000000  59 45 4c 4c 4f 57 20 53  55 42 4d 41 52 49 4e 45  |YELLOW SUBMARINE|\n\
+             00000010  00 ff 21                                          |..!|\n\
+             00000013\n");
+        assert_eq!(hexdump(b""), "00000000\n");
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        let detect = |data: &[u8]| detect_encoding(data).0;
+        assert_eq!(detect(include_bytes!("../data/4.txt")), Encoding::Hex);
+        assert_eq!(detect(include_bytes!("../data/6.txt")), Encoding::Base64);
+        assert_eq!(detect(include_bytes!("../data/17.txt")), Encoding::Base64);
+        assert_eq!(detect(b"0xdeadbeefcafe"), Encoding::Hex);
+        assert_eq!(detect(b"DE:AD:be:ef:ca:fe"), Encoding::Hex);
+        assert_eq!(decode_detected(b"0xDEADBEEF").unwrap().2, vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(decode_detected(b"de:ad:be:ef").unwrap().2, vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(detect(b"MZXW6YTBOI======"), Encoding::Base32);
+        assert_eq!(detect(b"-_-_PgA"), Encoding::Base64Url);
+        assert_eq!(detect(b"\x00\x01\xffhello"), Encoding::Raw);
+        assert_eq!(detect(b"hello, world"), Encoding::Raw);
+
+        let (_, confidence) = detect_encoding(include_bytes!("../data/8.txt"));
+        assert!(confidence > 0.99);
+        let (_, confidence) = detect_encoding(b"abcd");
+        assert!(confidence < 0.99);
+
+        let pem = b"-----BEGIN MESSAGE-----\n\
+            Proc-Type: 4,ENCRYPTED\n\
+            \n\
+            WUVMTE9XIFNV\n\
+            Qk1BUklORQ==\n\
+            -----END MESSAGE-----\n";
+        let (encoding, _, decoded) = decode_detected(pem).unwrap();
+        assert_eq!(encoding, Encoding::Pem);
+        assert_eq!(decoded, b"YELLOW SUBMARINE");
+
+        let (_, _, decoded) = decode_detected(include_bytes!("../data/7.txt")).unwrap();
+        assert_eq!(decoded, base64_decode_filter(include_bytes!("../data/7.txt")));
+    }
 }
@@ -0,0 +1,189 @@
+use codec::*;
+
+// Only the bytes that mean something in `key=value&key=value` (or `;`
+// separated) strings get escaped. Everything else, control bytes included,
+// goes through untouched.
+const RESERVED: &[u8] = b" %&;=";
+
+pub fn percent_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    for &b in bytes {
+        if RESERVED.contains(&b) {
+            out.push(b'%');
+            out.extend(base16_encode_upper(&[b]));
+        } else {
+            out.push(b);
+        }
+    }
+    out
+}
+
+pub fn percent_decode(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if i + 3 > bytes.len() {
+                return Err(format!("Truncated escape at byte {}", i));
+            }
+            // base16_decode would let `0x`, colons and spaces through.
+            let digits = &bytes[i + 1..i + 3];
+            if !digits.iter().all(|b| b.is_ascii_hexdigit()) {
+                return Err(format!("Bad escape at byte {}", i));
+            }
+            out.extend(base16_decode(digits)?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(out)
+}
+
+// An ordered list of key/value pairs. Keys are unique.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Cookie {
+    pairs: Vec<(Vec<u8>, Vec<u8>)>
+}
+
+impl Cookie {
+    pub fn new() -> Self {
+        Cookie { pairs: Vec::new() }
+    }
+
+    // Replaces the value if the key is already there.
+    pub fn set(&mut self, key: &[u8], value: &[u8]) {
+        match self.pairs.iter().position(|&(ref k, _)| k == key) {
+            Some(ix) => self.pairs[ix].1 = value.to_vec(),
+            None => self.pairs.push((key.to_vec(), value.to_vec()))
+        }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.pairs.iter()
+            .find(|&&(ref k, _)| k == key)
+            .map(|&(_, ref v)| &v[..])
+    }
+
+    pub fn pairs(&self) -> &[(Vec<u8>, Vec<u8>)] {
+        &self.pairs
+    }
+
+    pub fn encode(&self, separator: u8) -> Vec<u8> {
+        let mut out = Vec::new();
+        for &(ref k, ref v) in &self.pairs {
+            if !out.is_empty() {
+                out.push(separator);
+            }
+            out.extend(percent_encode(k));
+            out.push(b'=');
+            out.extend(percent_encode(v));
+        }
+        out
+    }
+
+    // Every pair needs exactly one `=` and a non-empty key, and a key can
+    // only appear once. An empty string is an empty cookie.
+    pub fn parse(bytes: &[u8], separator: u8) -> Result<Self, String> {
+        let mut cookie = Cookie::new();
+        if bytes.is_empty() {
+            return Ok(cookie);
+        }
+        for (ix, pair) in bytes.split(|&b| b == separator).enumerate() {
+            let parts: Vec<&[u8]> = pair.split(|&b| b == b'=').collect();
+            if parts.len() != 2 {
+                return Err(format!("Pair {} should have exactly one '='", ix + 1));
+            }
+            let key = percent_decode(parts[0])?;
+            let value = percent_decode(parts[1])?;
+            if key.is_empty() {
+                return Err(format!("Pair {} has an empty key", ix + 1));
+            }
+            if cookie.get(&key).is_some() {
+                return Err(format!("Duplicate key {}", String::from_utf8_lossy(&key)));
+            }
+            cookie.pairs.push((key, value));
+        }
+        Ok(cookie)
+    }
+}
+
+// The target for the ECB cut-and-paste: `email=...&uid=10&role=user`.
+pub fn profile_for(email: &[u8]) -> Vec<u8> {
+    let mut cookie = Cookie::new();
+    cookie.set(b"email", email);
+    cookie.set(b"uid", b"10");
+    cookie.set(b"role", b"user");
+    cookie.encode(b'&')
+}
+
+// The target for CBC bitflipping: `comment1=cooking%20MCs;userdata=...;
+// comment2=%20like%20a%20pound%20of%20bacon`.
+pub fn comments_for(userdata: &[u8]) -> Vec<u8> {
+    let mut cookie = Cookie::new();
+    cookie.set(b"comment1", b"cooking MCs");
+    cookie.set(b"userdata", userdata);
+    cookie.set(b"comment2", b" like a pound of bacon");
+    cookie.encode(b';')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_encoding() {
+        assert_eq!(percent_encode(b"a=b&c;d e%"), b"a%3Db%26c%3Bd%20e%25".to_vec());
+        assert_eq!(percent_encode(b"admin\x0b\x0b"), b"admin\x0b\x0b".to_vec());
+        assert_eq!(percent_decode(b"a%3db%26").unwrap(), b"a=b&".to_vec());
+        assert!(percent_decode(b"50%").is_err());
+        assert!(percent_decode(b"%zz").is_err());
+        assert!(percent_decode(b"ab%4").is_err());
+        assert!(percent_decode(b"%0x").is_err());
+        assert!(percent_decode(b"%::").is_err());
+        assert!(percent_decode(b"%  ").is_err());
+        assert!(percent_decode(b"% 4").is_err());
+        let all: Vec<u8> = (0..=255).collect();
+        assert_eq!(percent_decode(&percent_encode(&all)).unwrap(), all);
+    }
+
+    #[test]
+    fn test_parse() {
+        let cookie = Cookie::parse(b"foo=bar&baz=qux&zap=zazzle", b'&').unwrap();
+        assert_eq!(cookie.pairs().len(), 3);
+        assert_eq!(cookie.get(b"zap"), Some(&b"zazzle"[..]));
+        assert_eq!(cookie.get(b"nope"), None);
+        assert_eq!(Cookie::parse(b"", b'&').unwrap(), Cookie::new());
+        assert_eq!(Cookie::parse(b"a=", b'&').unwrap().get(b"a"), Some(&b""[..]));
+
+        assert!(Cookie::parse(b"role=user&role=admin", b'&').is_err());
+        assert!(Cookie::parse(b"a=b&", b'&').is_err());
+        assert!(Cookie::parse(b"a=b&c", b'&').is_err());
+        assert!(Cookie::parse(b"a=b=c", b'&').is_err());
+        assert!(Cookie::parse(b"=b", b'&').is_err());
+        assert!(Cookie::parse(b"a=%4", b'&').is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut cookie = Cookie::new();
+        cookie.set(b"email", b"foo@bar.com&role=admin");
+        cookie.set(b"weird key;", b"%%==");
+        cookie.set(b"email", b"me@example.com");
+        for &sep in &[b'&', b';'] {
+            let encoded = cookie.encode(sep);
+            assert_eq!(Cookie::parse(&encoded, sep).unwrap(), cookie);
+        }
+        assert_eq!(cookie.get(b"email"), Some(&b"me@example.com"[..]));
+
+        assert_eq!(profile_for(b"foo@bar.com"), b"email=foo@bar.com&uid=10&role=user".to_vec());
+        let profile = Cookie::parse(&profile_for(b"foo@bar.com&role=admin"), b'&').unwrap();
+        assert_eq!(profile.get(b"role"), Some(&b"user"[..]));
+        assert_eq!(
+            comments_for(b";admin=true"),
+            b"comment1=cooking%20MCs;userdata=%3Badmin%3Dtrue;\
+              comment2=%20like%20a%20pound%20of%20bacon".to_vec()
+        );
+    }
+}
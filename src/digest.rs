@@ -0,0 +1,152 @@
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use std::marker::PhantomData;
+
+// What MD4, SHA-1 and SHA-256 have in common: Merkle-Damgard over 64-byte
+// blocks, with the message length in bits at the end of the padding. The
+// chaining state is the digest, so any digest can be picked up and carried
+// on from.
+pub trait Digest: Clone {
+    fn block_size() -> usize;
+    fn output_size() -> usize;
+    fn new() -> Self;
+
+    // Carries on from a finished digest of `length` bytes, padding included,
+    // so `length` must be a whole number of blocks.
+    fn from_digest(digest: &[u8], length: u64) -> Self;
+
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> Vec<u8>;
+
+    // What gets appended to a message of `length` bytes.
+    fn padding(length: u64) -> Vec<u8>;
+
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut hasher = Self::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+// The part that differs from one hash to the next: the starting state, the
+// compression function, and which way round words are written.
+pub trait Compress: Clone {
+    fn initial_state() -> Vec<u32>;
+    fn big_endian() -> bool;
+    fn compress(state: &mut [u32], block: &[u8]);
+}
+
+// Everything else: buffering input into blocks, counting how much went in,
+// and padding at the end. Sha1, Md4 and Sha256 are this over their own
+// compression functions.
+#[derive(Debug, Clone)]
+pub struct MdHash<C> {
+    state: Vec<u32>,
+    buffer: Vec<u8>,
+    length: u64,
+    compress: PhantomData<C>
+}
+
+impl<C> MdHash<C> where C: Compress {
+    // Picks up from any point: `state` is the chaining words and `length` is
+    // how many bytes went into them, which must be a whole number of blocks.
+    pub fn from_state(state: &[u32], length: u64) -> Self {
+        assert!(length % 64 == 0, "length must be a multiple of the block size");
+        assert_eq!(state.len(), C::initial_state().len());
+        MdHash { state: state.to_vec(), buffer: Vec::new(), length: length, compress: PhantomData }
+    }
+
+    pub fn state(&self) -> &[u32] {
+        &self.state
+    }
+}
+
+impl<C> Digest for MdHash<C> where C: Compress {
+    fn block_size() -> usize {
+        64
+    }
+
+    fn output_size() -> usize {
+        4 * C::initial_state().len()
+    }
+
+    fn new() -> Self {
+        MdHash::from_state(&C::initial_state(), 0)
+    }
+
+    fn from_digest(digest: &[u8], length: u64) -> Self {
+        assert_eq!(digest.len(), Self::output_size());
+        let mut state = vec![0; digest.len() / 4];
+        if C::big_endian() {
+            BigEndian::read_u32_into(digest, &mut state);
+        } else {
+            LittleEndian::read_u32_into(digest, &mut state);
+        }
+        MdHash::from_state(&state, length)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.length += data.len() as u64;
+        let blocks = take_blocks(&mut self.buffer, data, 64);
+        for block in blocks.chunks(64) {
+            C::compress(&mut self.state, block);
+        }
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        let padding = Self::padding(self.length);
+        self.update(&padding);
+        let mut out = vec![0; Self::output_size()];
+        if C::big_endian() {
+            BigEndian::write_u32_into(&self.state, &mut out);
+        } else {
+            LittleEndian::write_u32_into(&self.state, &mut out);
+        }
+        out
+    }
+
+    fn padding(length: u64) -> Vec<u8> {
+        md_padding(length, C::big_endian())
+    }
+}
+
+// 0x80, zeroes up to 56 mod 64, then the length in bits as a u64.
+pub fn md_padding(length: u64, big_endian: bool) -> Vec<u8> {
+    let zeroes = (55 + 64 - (length % 64) as usize) % 64;
+    let mut out = vec![0x80];
+    out.extend(vec![0; zeroes]);
+    let mut bits = [0; 8];
+    if big_endian {
+        BigEndian::write_u64(&mut bits, length.wrapping_mul(8));
+    } else {
+        LittleEndian::write_u64(&mut bits, length.wrapping_mul(8));
+    }
+    out.extend(&bits);
+    out
+}
+
+// Adds `data` to what's buffered and takes out every whole block.
+pub fn take_blocks(buffer: &mut Vec<u8>, data: &[u8], block_size: usize) -> Vec<u8> {
+    buffer.extend(data);
+    let full = buffer.len() / block_size * block_size;
+    buffer.drain(..full).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_md_padding() {
+        for len in 0..200 {
+            assert_eq!((len + md_padding(len as u64, true).len()) % 64, 0);
+        }
+        assert_eq!(md_padding(55, true), b"\x80\0\0\0\0\0\0\x01\xb8".to_vec());
+        assert_eq!(md_padding(55, false), b"\x80\xb8\x01\0\0\0\0\0\0".to_vec());
+        assert_eq!(md_padding(56, true).len(), 72);
+
+        let mut buffer = b"abc".to_vec();
+        assert_eq!(take_blocks(&mut buffer, &[0; 130], 64).len(), 128);
+        assert_eq!(buffer, vec![0; 5]);
+    }
+}
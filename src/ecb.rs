@@ -0,0 +1,156 @@
+use oracle::*;
+use pkcs::*;
+
+pub fn ecb_block_size<O>(ora: &O) -> usize where O: EncryptionOracle {
+    let bytes = &[b'A'; 255];
+    let mut out_length = None;
+    let mut block_size = None;
+    for i in 1..=255 {
+        let enc = ora.encrypt(&bytes[..i]);
+        match out_length {
+            Some(l) if enc.len() > l => {
+                block_size = Some(enc.len() - l);
+                break;
+            }
+            Some(_) => { },
+            None => { out_length = Some(enc.len()); }
+        }
+    }
+    block_size.unwrap()
+}
+
+// Return the first block that matches the next one, and its position in units
+// of block_size.
+fn adj_blocks_match<O>(known_pt: &[u8], block_size: usize, oracle: &O) -> Option<(usize, Vec<u8>)>
+    where O: EncryptionOracle {
+    let bytes = oracle.encrypt(known_pt);
+    let block_count = bytes.len() / block_size;
+    for i in 0..block_count-1 {
+        let first  = &bytes[block_size * i      ..block_size * (i + 1)];
+        let second = &bytes[block_size * (i + 1)..block_size * (i + 2)];
+        if  first == second {
+            return Some((i, first.to_vec()));
+        }
+    }
+    None
+}
+
+pub fn guess_prefix_length<O>(oracle: &O, block_size: usize) -> usize where O: EncryptionOracle {
+    // Passing this into the oracle is guaranteed to result in two adjacent
+    // matching blocks, so we can safely unwrap.
+    let mut prefix_test = vec![b'A'; block_size*3];
+    let res = adj_blocks_match(&prefix_test, block_size, oracle).unwrap();
+    let ix = res.0;
+    let to_match = res.1;
+
+    // (To be safe, we should repeat prefix_test with vec![b'B'; block_size*3]
+    // which will let us confirm where our injected text is going in case
+    // the prefix or the plaintext results in false positives.
+    // For instance, there's a chance that the prefix contains matching
+    // adjacent blocks, or that the last fractional block of prefix text is
+    // all b'A'.)
+
+    // Let's remove elements until we stop having two adjacent matching blocks
+    // We gotta be careful here because adj_blocks_match might find a match
+    // due to the actual unknown plaintext, but we only want to find matches
+    // due to the attacker-supplied plaintext.
+    while Some(&to_match) == adj_blocks_match(&prefix_test, block_size, oracle).map(|x| x.1).as_ref() {
+        prefix_test.pop();
+    }
+    let n = prefix_test.len() + 1;
+    let prefix_pad = n % block_size;
+
+    ix * block_size - prefix_pad
+}
+
+// How many bytes the oracle appends after our input. Once input + suffix
+// fills the last block exactly, one more byte of input adds a whole block of
+// padding.
+pub fn guess_suffix_length<O>(oracle: &O, block_size: usize, prefix_length: usize) -> usize
+    where O: EncryptionOracle {
+    let base = oracle.encrypt(b"").len();
+    let grows_at = (1..=block_size)
+        .find(|&n| oracle.encrypt(&vec![b'A'; n]).len() > base)
+        .unwrap();
+    base - grows_at - prefix_length
+}
+
+// Forges a record that ends in `new` instead of `old`, given an ECB oracle
+// that puts our input somewhere before `old`. It works so long as the oracle
+// passes `new` and its padding bytes through unescaped:
+//  - line our input up so that `new`, padded, starts on a block boundary
+//  - line our input up so that `old` starts on a block boundary
+//  - swap the blocks holding `old` for the blocks holding padded `new`
+// As with bitflip, `check` says whether the forgery worked, e.g. by handing
+// it to whatever decrypts and parses the record.
+pub fn cut_and_paste<O, F>(oracle: &O, old: &[u8], new: &[u8], check: F) -> Result<Vec<u8>, String>
+    where O: EncryptionOracle, F: Fn(&[u8]) -> bool {
+    let block_size = ecb_block_size(oracle);
+    let prefix_length = guess_prefix_length(oracle, block_size);
+    let suffix_length = guess_suffix_length(oracle, block_size, prefix_length);
+
+    let align = (block_size - prefix_length % block_size) % block_size;
+    let target = pkcs7(new, (new.len() / block_size + 1) * block_size).unwrap();
+    let mut input = vec![b'A'; align];
+    input.extend(&target);
+    let start = prefix_length + align;
+    let blocks = oracle.encrypt(&input)[start..start + target.len()].to_vec();
+
+    let before_old = (prefix_length + suffix_length).checked_sub(old.len())
+        .ok_or_else(|| format!(
+            "{} bytes to replace, but the oracle only adds {}",
+            old.len(), prefix_length + suffix_length))?;
+    let filler = (block_size - before_old % block_size) % block_size;
+    let mut forged = oracle.encrypt(&vec![b'A'; filler]);
+    forged.truncate(before_old + filler);
+    forged.extend(blocks);
+    if check(&forged) {
+        Ok(forged)
+    } else {
+        Err("Forged ciphertext didn't pass the check".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cookie::*;
+
+    #[test]
+    fn test_guess_lengths() {
+        for &(prefix, suffix) in &[(0, 0), (6, 17), (16, 16), (37, 3)] {
+            let prefix_bytes: Vec<u8> = (0..prefix as u8).collect();
+            let oracle = Oracle::new(Mode::ECB)
+                .with_prefix(&prefix_bytes)
+                .with_suffix(&vec![b's'; suffix]);
+            assert_eq!(ecb_block_size(&oracle), 16);
+            assert_eq!(guess_prefix_length(&oracle, 16), prefix);
+            assert_eq!(guess_suffix_length(&oracle, 16, prefix), suffix);
+        }
+    }
+
+    #[test]
+    fn test_cut_and_paste() {
+        let new_roles: &[&[u8]] = &[b"admin", b"administrator-of-everything", b"0123456789abcdef"];
+        for &prefix in &[0, 5, 16, 21] {
+            for &role in new_roles {
+                let oracle = Oracle::new(Mode::ECB).with_prefix(&vec![b'x'; prefix]);
+                let encrypt = |email: &[u8]| oracle.encrypt(&profile_for(email));
+                let profile = |ct: &[u8]| {
+                    let dec = oracle.decrypt(ct).unwrap();
+                    Cookie::parse(&dec[prefix..], b'&').unwrap()
+                };
+                let is_role = |ct: &[u8]| profile(ct).get(b"role") == Some(role);
+                let forged = cut_and_paste(&encrypt, b"user", role, is_role).unwrap();
+                assert_eq!(profile(&forged).get(b"role"), Some(role));
+                assert_eq!(profile(&forged).get(b"uid"), Some(&b"10"[..]));
+            }
+        }
+
+        let oracle = Oracle::new(Mode::ECB);
+        let encrypt = |email: &[u8]| oracle.encrypt(&profile_for(email));
+        assert!(cut_and_paste(&encrypt, b"user", b"admin", |_: &[u8]| false).is_err());
+        let too_long = vec![b'u'; 100];
+        assert!(cut_and_paste(&encrypt, &too_long, b"admin", |_: &[u8]| true).is_err());
+    }
+}
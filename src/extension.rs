@@ -0,0 +1,95 @@
+use digest::*;
+
+// The naive MAC: H(key || message). Length extension breaks it.
+pub fn secret_prefix_mac<D>(key: &[u8], message: &[u8]) -> Vec<u8> where D: Digest {
+    let mut hasher = D::new();
+    hasher.update(key);
+    hasher.update(message);
+    hasher.finalize()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Forgery {
+    pub message: Vec<u8>,
+    pub mac: Vec<u8>,
+    pub secret_length: usize
+}
+
+// If the secret is `secret_length` bytes, then `mac` is the state of the hash
+// after secret || message || glue, where glue is the padding the hash added.
+// So we can carry on hashing `suffix` from there, and the result is a valid
+// MAC for message || glue || suffix.
+pub fn extend<D>(mac: &[u8], message: &[u8], secret_length: usize, suffix: &[u8]) -> Forgery
+    where D: Digest {
+    let hashed = (secret_length + message.len()) as u64;
+    let glue = D::padding(hashed);
+    let mut hasher = D::from_digest(mac, hashed + glue.len() as u64);
+    hasher.update(suffix);
+
+    let mut forged = message.to_vec();
+    forged.extend(&glue);
+    forged.extend(suffix);
+    Forgery { message: forged, mac: hasher.finalize(), secret_length: secret_length }
+}
+
+// The glue depends on the secret's length, so try each length from
+// `min_secret` to `max_secret` inclusive until `verify` accepts one.
+pub fn length_extension<D, F>(mac: &[u8], message: &[u8], min_secret: usize, max_secret: usize,
+                              suffix: &[u8], verify: F) -> Option<Forgery>
+    where D: Digest, F: Fn(&[u8], &[u8]) -> bool {
+    (min_secret..=max_secret)
+        .map(|secret_length| extend::<D>(mac, message, secret_length, suffix))
+        .find(|forgery| verify(&forgery.message, &forgery.mac))
+}
+
+// Plays both sides: MACs `message` under `key`, then forges `suffix` onto it
+// using only the MAC and a server that checks MACs, as in challenges 29 and
+// 30. The key's length is found along the way.
+pub fn forge_with_key<D>(key: &[u8], message: &[u8], max_secret: usize, suffix: &[u8])
+    -> Option<Forgery> where D: Digest {
+    let mac = secret_prefix_mac::<D>(key, message);
+    let verify = |message: &[u8], mac: &[u8]| secret_prefix_mac::<D>(key, message) == mac;
+    length_extension::<D, _>(&mac, message, 0, max_secret, suffix, verify)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md4::*;
+    use sha1::*;
+    use sha256::*;
+    use rand;
+
+    fn forge<D>() where D: Digest {
+        let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+        for _ in 0..5 {
+            let key_length = rand::random::<usize>() % 40;
+            let key: Vec<u8> = (0..key_length).map(|_| rand::random()).collect();
+            let verify = |message: &[u8], mac: &[u8]| secret_prefix_mac::<D>(&key, message) == mac;
+            let mac = secret_prefix_mac::<D>(&key, message);
+
+            let forgery = forge_with_key::<D>(&key, message, 64, b";admin=true").unwrap();
+            assert_eq!(forgery.secret_length, key_length);
+            assert!(forgery.message.starts_with(message));
+            assert!(forgery.message.ends_with(b";admin=true"));
+            assert!(verify(&forgery.message, &forgery.mac));
+
+            assert!(length_extension::<D, _>(&mac, message, 41, 64, b";admin=true", &verify)
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn test_length_extension() {
+        forge::<Sha1>();
+        forge::<Sha256>();
+        forge::<Md4>();
+    }
+
+    #[test]
+    fn test_secret_prefix_mac() {
+        let mac = secret_prefix_mac::<Sha1>(b"YELLOW SUBMARINE", b"message");
+        assert_eq!(mac, sha1(b"YELLOW SUBMARINEmessage"));
+        assert!(secret_prefix_mac::<Sha1>(b"YELLOW SUBMARINE", b"massage") != mac);
+    }
+}
@@ -0,0 +1,169 @@
+use digest::*;
+
+use std::time::{Duration, Instant};
+
+// RFC 2104: H((K ^ opad) || H((K ^ ipad) || message)), with K padded out to
+// the block size, or hashed first if it's longer than a block.
+pub fn hmac<D>(key: &[u8], message: &[u8]) -> Vec<u8> where D: Digest {
+    let mut block = if key.len() > D::block_size() { D::digest(key) } else { key.to_vec() };
+    block.resize(D::block_size(), 0);
+
+    let mut inner = D::new();
+    inner.update(&block.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>());
+    inner.update(message);
+
+    let mut outer = D::new();
+    outer.update(&block.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>());
+    outer.update(&inner.finalize());
+    outer.finalize()
+}
+
+// Gives up at the first byte that differs, and waits for `delay` after every
+// byte that doesn't, so how long it takes says how much of `b` is right. It
+// spins rather than sleeps: sleeping oversleeps by a millisecond or so, which
+// drowns out short delays.
+pub fn insecure_compare(a: &[u8], b: &[u8], delay: Duration) -> bool {
+    for (x, y) in a.iter().zip(b) {
+        if x != y {
+            return false;
+        }
+        let start = Instant::now();
+        while start.elapsed() < delay { }
+    }
+    a.len() == b.len()
+}
+
+// Checks HMAC-D(key, message) with insecure_compare. This is the server side
+// of challenges 31 and 32. The MAC can be cut short with `with_mac_length`,
+// since every byte of it costs the attack a lot of waiting.
+#[derive(Debug, Clone)]
+pub struct LeakyVerifier {
+    key: Vec<u8>,
+    delay: Duration,
+    mac_length: Option<usize>
+}
+
+impl LeakyVerifier {
+    pub fn new(key: &[u8], delay: Duration) -> Self {
+        LeakyVerifier { key: key.to_vec(), delay: delay, mac_length: None }
+    }
+
+    pub fn with_mac_length(mut self, length: usize) -> Self {
+        self.mac_length = Some(length);
+        self
+    }
+
+    pub fn verify<D>(&self, message: &[u8], mac: &[u8]) -> bool where D: Digest {
+        let mut expected = hmac::<D>(&self.key, message);
+        if let Some(length) = self.mac_length {
+            expected.truncate(length);
+        }
+        insecure_compare(&expected, mac, self.delay)
+    }
+}
+
+fn fastest_time<F>(rounds: usize, f: F) -> Duration where F: Fn() -> bool {
+    (0..rounds)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+// Works out a `length`-byte MAC that `verify` accepts, one byte at a time:
+// the right byte is the one that makes `verify` take longest. Noise only ever
+// makes a call slower, so each guess is timed `rounds` times and we go by the
+// fastest. Even so, a wrong byte can come out on top, so the eight slowest
+// get timed again, five times as often, before we commit. The last byte
+// doesn't need timing, since `verify` says yes to exactly one of them.
+pub fn timing_attack<F>(length: usize, rounds: usize, verify: F) -> Option<Vec<u8>>
+    where F: Fn(&[u8]) -> bool {
+    if length == 0 {
+        return if verify(&[]) { Some(Vec::new()) } else { None };
+    }
+    let mut guess = vec![0; length];
+    for i in 0..length - 1 {
+        let mut times: Vec<(Duration, u8)> = (0..=255u8)
+            .map(|b| {
+                guess[i] = b;
+                (fastest_time(rounds, || verify(&guess)), b)
+            })
+            .collect();
+        times.sort();
+        let (_, best) = times[248..].iter()
+            .map(|&(_, b)| {
+                guess[i] = b;
+                (fastest_time(rounds * 5, || verify(&guess)), b)
+            })
+            .max()
+            .unwrap();
+        guess[i] = best;
+    }
+    (0..=255u8)
+        .map(|b| {
+            guess[length - 1] = b;
+            guess.clone()
+        })
+        .find(|mac| verify(mac))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::*;
+    use md4::*;
+    use sha1::*;
+    use sha256::*;
+
+    fn hex_hmac<D>(key: &[u8], message: &[u8]) -> Vec<u8> where D: Digest {
+        base16_encode(&hmac::<D>(key, message))
+    }
+
+    #[test]
+    fn test_hmac_vectors() {
+        // RFC 2202
+        assert_eq!(hex_hmac::<Sha1>(&[0x0b; 20], b"Hi There"),
+                   b"b617318655057264e28bc0b6fb378c8ef146be00".to_vec());
+        assert_eq!(hex_hmac::<Sha1>(b"Jefe", b"what do ya want for nothing?"),
+                   b"effcdf6ae5eb2fa2d27416d5f184df9c259a7c79".to_vec());
+        assert_eq!(hex_hmac::<Sha1>(&[0xaa; 80], b"Test Using Larger Than Block-Size Key - Hash Key First"),
+                   b"aa4ae5e15272d00e95705637ce8a3b55ed402112".to_vec());
+
+        // RFC 4231
+        assert_eq!(hex_hmac::<Sha256>(&[0x0b; 20], b"Hi There"),
+                   b"b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7".to_vec());
+        assert_eq!(hex_hmac::<Sha256>(b"Jefe", b"what do ya want for nothing?"),
+                   b"5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843".to_vec());
+        assert_eq!(hex_hmac::<Sha256>(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First"),
+                   b"60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54".to_vec());
+
+        assert_eq!(hmac::<Md4>(b"key", b"message").len(), 16);
+    }
+
+    #[test]
+    fn test_insecure_compare() {
+        let verifier = LeakyVerifier::new(b"YELLOW SUBMARINE", Duration::new(0, 0));
+        let mac = hmac::<Sha1>(b"YELLOW SUBMARINE", b"file");
+        assert!(verifier.verify::<Sha1>(b"file", &mac));
+        assert!(!verifier.verify::<Sha1>(b"file", &mac[..19]));
+        assert!(!verifier.verify::<Sha1>(b"other file", &mac));
+        assert!(!insecure_compare(b"abc", b"abd", Duration::new(0, 0)));
+
+        let verifier = verifier.with_mac_length(4);
+        assert!(verifier.verify::<Sha1>(b"file", &mac[..4]));
+        assert!(!verifier.verify::<Sha1>(b"file", &mac));
+    }
+
+    #[test]
+    fn test_timing_attack() {
+        // Only three bytes, or this would take minutes. The delay is long
+        // enough that a busy machine doesn't hide it: a wrong byte would have
+        // to be held up by 5ms every time it was timed to beat the right one.
+        let secret = [0x5e, 0xc8, 0x37];
+        let verify = |mac: &[u8]| insecure_compare(&secret, mac, Duration::from_millis(5));
+        assert_eq!(timing_attack(3, 2, verify), Some(secret.to_vec()));
+    }
+}
@@ -0,0 +1,353 @@
+use codec::*;
+use cookie::*;
+use digest::*;
+use hmac::*;
+use oracle::*;
+
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::str::from_utf8;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// Just enough HTTP to put the oracles on the other side of a socket: GET
+// requests with a query string, answered with a status and a body. Binary
+// parameters and bodies are hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub path: String,
+    pub query: Cookie
+}
+
+impl Request {
+    // Takes the request line, e.g. `GET /test?file=foo&signature=46b4 HTTP/1.1`.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = line.split(' ').collect();
+        if parts.len() != 3 || parts[0] != "GET" || !parts[2].starts_with("HTTP/") {
+            return Err(format!("Bad request line: {}", line));
+        }
+        let (path, query) = match parts[1].find('?') {
+            Some(ix) => (&parts[1][..ix], &parts[1][ix + 1..]),
+            None => (parts[1], "")
+        };
+        Ok(Request { path: path.to_string(), query: Cookie::parse(query.as_bytes(), b'&')? })
+    }
+
+    fn hex_param(&self, key: &str) -> Result<Vec<u8>, Response> {
+        let value = self.query.get(key.as_bytes())
+            .ok_or_else(|| Response::error(400, &format!("Missing parameter {}", key)))?;
+        base16_decode(value).map_err(|err| Response::error(400, &err))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Vec<u8>
+}
+
+impl Response {
+    pub fn ok(body: &[u8]) -> Self {
+        Response { status: 200, body: body.to_vec() }
+    }
+
+    pub fn error(status: u16, message: &str) -> Self {
+        Response { status: status, body: message.as_bytes().to_vec() }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            _   => "Internal Server Error"
+        };
+        let mut out = format!(
+            "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status, reason, self.body.len()
+        ).into_bytes();
+        out.extend(&self.body);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let split = bytes.windows(4).position(|w| w == b"\r\n\r\n")
+            .ok_or("Response has no end of headers")?;
+        let head = from_utf8(&bytes[..split]).map_err(|err| err.to_string())?;
+        let status = head.split(' ').nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| format!("Bad status line: {}", head.lines().next().unwrap_or("")))?;
+        Ok(Response { status: status, body: bytes[split + 4..].to_vec() })
+    }
+}
+
+pub type Handler = Box<dyn Fn(&Request) -> Response + Send + Sync>;
+
+pub struct Router {
+    routes: Vec<(String, Handler)>
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router { routes: Vec::new() }
+    }
+
+    pub fn route(mut self, path: &str, handler: Handler) -> Self {
+        self.routes.push((path.to_string(), handler));
+        self
+    }
+
+    pub fn handle(&self, request: &Request) -> Response {
+        match self.routes.iter().find(|&&(ref path, _)| path == &request.path) {
+            Some(&(_, ref handler)) => handler(request),
+            None => Response::error(404, &format!("No such page: {}", request.path))
+        }
+    }
+}
+
+// GET /path?input=<hex> gives the hex of the encryption.
+pub fn encryption_handler<O>(oracle: O) -> Handler
+    where O: EncryptionOracle + Send + Sync + 'static {
+    Box::new(move |request: &Request| {
+        match request.hex_param("input") {
+            Ok(input) => Response::ok(&base16_encode(&oracle.encrypt(&input))),
+            Err(response) => response
+        }
+    })
+}
+
+// GET /path?input=<hex> is a 200 for good padding and a 500 for bad.
+pub fn padding_handler<O>(oracle: O) -> Handler
+    where O: PaddingOracle + Send + Sync + 'static {
+    Box::new(move |request: &Request| {
+        match request.hex_param("input") {
+            Ok(ref input) if oracle.padding_ok(input) => Response::ok(b"ok"),
+            Ok(_) => Response::error(500, "bad padding"),
+            Err(response) => response
+        }
+    })
+}
+
+// GET /path?file=<name>&signature=<hex>, as in challenge 31: a 200 if the
+// signature is the HMAC of the file name and a 500 if not. The comparison
+// leaks through its timing.
+pub fn hmac_handler<D>(verifier: LeakyVerifier) -> Handler
+    where D: Digest + 'static {
+    Box::new(move |request: &Request| {
+        let file = match request.query.get(b"file") {
+            Some(file) => file.to_vec(),
+            None => return Response::error(400, "Missing parameter file")
+        };
+        match request.hex_param("signature") {
+            Ok(ref mac) if verifier.verify::<D>(&file, mac) => Response::ok(b"ok"),
+            Ok(_) => Response::error(500, "bad signature"),
+            Err(response) => response
+        }
+    })
+}
+
+// Serves a router on 127.0.0.1, and never anywhere else, taking one request
+// at a time so as not to muddy any timings. It stops when dropped.
+pub struct Server {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl Server {
+    // On whatever port is free.
+    pub fn spawn(router: Router) -> io::Result<Self> {
+        Server::spawn_on(0, router)
+    }
+
+    pub fn spawn_on(port: u16, router: Router) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let _ = serve_one(&router, stream);
+                }
+            }
+        });
+        Ok(Server { addr: addr, stop: stop, thread: Some(thread) })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // Serves until the process is killed.
+    pub fn run(mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.stop.store(true, Ordering::SeqCst);
+            // Wake the listener up so it sees the flag.
+            let _ = TcpStream::connect(self.addr);
+            let _ = thread.join();
+        }
+    }
+}
+
+// How long a client gets to send its request or take the response before we
+// give up on it and move on to the next one.
+fn client_timeout() -> Duration {
+    Duration::from_secs(2)
+}
+
+fn serve_one(router: &Router, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(client_timeout()))?;
+    stream.set_write_timeout(Some(client_timeout()))?;
+    let mut line = String::new();
+    {
+        let mut reader = BufReader::new(&stream);
+        reader.read_line(&mut line)?;
+        // Skip the headers; nothing here needs them.
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? <= 2 {
+                break;
+            }
+        }
+    }
+    let response = match Request::parse(line.trim()) {
+        Ok(request) => router.handle(&request),
+        Err(err) => Response::error(400, &err)
+    };
+    stream.write_all(&response.to_bytes())
+}
+
+// Makes one GET request. Parameters are percent-encoded as in the cookie
+// module, so binary values should be hex.
+pub fn get(addr: SocketAddr, path: &str, params: &[(&str, &[u8])]) -> io::Result<Response> {
+    let mut query = Cookie::new();
+    for &(key, value) in params {
+        query.set(key.as_bytes(), value);
+    }
+    let mut stream = TcpStream::connect(addr)?;
+    stream.write_all(format!("GET {}?", path).as_bytes())?;
+    stream.write_all(&query.encode(b'&'))?;
+    stream.write_all(format!(" HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", addr).as_bytes())?;
+    let mut bytes = Vec::new();
+    stream.read_to_end(&mut bytes)?;
+    Response::from_bytes(&bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+// The client side: an oracle that lives at `path` on a server. A request that
+// fails outright panics, since the oracle traits have nowhere to put the
+// error.
+#[derive(Debug, Clone)]
+pub struct Remote {
+    addr: SocketAddr,
+    path: String
+}
+
+impl Remote {
+    pub fn new(addr: SocketAddr, path: &str) -> Self {
+        Remote { addr: addr, path: path.to_string() }
+    }
+
+    pub fn get(&self, params: &[(&str, &[u8])]) -> Response {
+        get(self.addr, &self.path, params).expect("request to oracle failed")
+    }
+
+    // For hmac_handler.
+    pub fn verify_hmac(&self, file: &[u8], mac: &[u8]) -> bool {
+        self.get(&[("file", file), ("signature", &base16_encode(mac))]).status == 200
+    }
+}
+
+impl EncryptionOracle for Remote {
+    fn encrypt(&self, pt: &[u8]) -> Vec<u8> {
+        let response = self.get(&[("input", &base16_encode(pt))]);
+        assert_eq!(response.status, 200, "{}", String::from_utf8_lossy(&response.body));
+        base16_decode(&response.body).expect("oracle sent back bad hex")
+    }
+}
+
+impl PaddingOracle for Remote {
+    fn padding_ok(&self, ct: &[u8]) -> bool {
+        self.get(&[("input", &base16_encode(ct))]).status == 200
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha1::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_request_response() {
+        let request = Request::parse("GET /test?file=foo&signature=46b4 HTTP/1.1").unwrap();
+        assert_eq!(request.path, "/test");
+        assert_eq!(request.query.get(b"file"), Some(&b"foo"[..]));
+        assert_eq!(Request::parse("GET /plain HTTP/1.0").unwrap().query, Cookie::new());
+        assert!(Request::parse("POST /test HTTP/1.1").is_err());
+        assert!(Request::parse("GET /test?a=b=c HTTP/1.1").is_err());
+
+        let response = Response::error(500, "nope");
+        assert_eq!(Response::from_bytes(&response.to_bytes()).unwrap(), response);
+        assert!(Response::from_bytes(b"HTTP/1.1 200 OK\r\n").is_err());
+    }
+
+    #[test]
+    fn test_server() {
+        let oracle = Oracle::new(Mode::CBC).with_prefix(b"prefix");
+        let key = b"YELLOW SUBMARINE";
+        let verifier = LeakyVerifier::new(key, Duration::new(0, 0));
+        let router = Router::new()
+            .route("/encrypt", encryption_handler(oracle.clone()))
+            .route("/padding", padding_handler(oracle.clone()))
+            .route("/test", hmac_handler::<Sha1>(verifier));
+        let server = Server::spawn(router).unwrap();
+        assert!(server.addr().ip().is_loopback());
+
+        let encrypt = Remote::new(server.addr(), "/encrypt");
+        let ct = encrypt.encrypt(b"hello");
+        assert_eq!(ct, oracle.encrypt(b"hello"));
+
+        let padding = Remote::new(server.addr(), "/padding");
+        assert!(padding.padding_ok(&ct));
+        let mut bad = ct.clone();
+        bad[15] ^= 1;
+        assert!(!padding.padding_ok(&bad));
+
+        let test = Remote::new(server.addr(), "/test");
+        let mac = hmac::<Sha1>(key, b"foo");
+        assert!(test.verify_hmac(b"foo", &mac));
+        assert!(!test.verify_hmac(b"bar", &mac));
+
+        assert_eq!(encrypt.get(&[("input", b"zz")]).status, 400);
+        assert_eq!(Remote::new(server.addr(), "/nowhere").get(&[]).status, 404);
+    }
+
+    #[test]
+    fn test_stalled_client() {
+        let router = Router::new().route("/encrypt", encryption_handler(Oracle::new(Mode::ECB)));
+        let server = Server::spawn(router).unwrap();
+        let start = Instant::now();
+        // Connects and then says nothing, which would otherwise hold up the
+        // server for good.
+        let _stalled = TcpStream::connect(server.addr()).unwrap();
+        let encrypt = Remote::new(server.addr(), "/encrypt");
+        assert_eq!(encrypt.encrypt(b"hello").len(), 16);
+        assert!(start.elapsed() < client_timeout() * 2);
+    }
+}
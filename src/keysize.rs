@@ -0,0 +1,143 @@
+use xor::*;
+
+use std::cmp::Ordering;
+
+// Estimators for the key size of a repeating XOR (or any other periodic
+// cipher). Each one tries every key size in min..=max and returns them with
+// their scores, best first. A key size of 0 means nothing, so ranges that
+// start there start at 1 instead. Multiples of the real key size tend to score
+// nearly as well as the key size itself, which is what `guess_keysize` is
+// for.
+
+fn sort_by_score(mut scores: Vec<(usize, f32)>, higher_is_better: bool) -> Vec<(usize, f32)> {
+    scores.sort_by(|a, b| {
+        let ord = a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal);
+        if higher_is_better { ord.reverse() } else { ord }
+    });
+    scores
+}
+
+// Hamming distance between every pair of keysize-length blocks, divided by
+// the key size and averaged. Lower is better: blocks XORed with the same key
+// differ only as much as their plaintexts do.
+pub fn hamming_keysizes(bytes: &[u8], min: usize, max: usize) -> Vec<(usize, f32)> {
+    let scores = (min.max(1)..=max)
+        .filter(|&keysize| bytes.len() >= keysize * 2)
+        .map(|keysize| {
+            let blocks: Vec<&[u8]> = bytes.chunks(keysize)
+                .filter(|block| block.len() == keysize)
+                .collect();
+            let mut total = 0;
+            let mut pairs = 0;
+            for i in 0..blocks.len() {
+                for j in i + 1..blocks.len() {
+                    total += hamming(blocks[i], blocks[j]).unwrap();
+                    pairs += 1;
+                }
+            }
+            (keysize, total as f32 / pairs as f32 / keysize as f32)
+        })
+        .collect();
+    sort_by_score(scores, false)
+}
+
+// The chance that two bytes picked from the same column are equal, averaged
+// over the columns. Higher is better: about 0.004 for random bytes, and
+// closer to 0.06 for English.
+pub fn coincidence_keysizes(bytes: &[u8], min: usize, max: usize) -> Vec<(usize, f32)> {
+    let scores = (min.max(1)..=max)
+        .filter(|&keysize| bytes.len() >= keysize * 2)
+        .map(|keysize| {
+            let columns = transpose(bytes, keysize);
+            let total: f32 = columns.iter().map(|column| index_of_coincidence(column)).sum();
+            (keysize, total / columns.len() as f32)
+        })
+        .collect();
+    sort_by_score(scores, true)
+}
+
+pub fn index_of_coincidence(s: &[u8]) -> f32 {
+    if s.len() < 2 {
+        return 0.0;
+    }
+    let mut counts = [0u32; 256];
+    for &b in s {
+        counts[b as usize] += 1;
+    }
+    let pairs: u32 = counts.iter().map(|&n| n * n.saturating_sub(1)).sum();
+    pairs as f32 / (s.len() * (s.len() - 1)) as f32
+}
+
+// Shift the ciphertext against itself and count how often bytes line up.
+// When the shift is a multiple of the key size, matching bytes mean matching
+// plaintext, so this is the same signal Kasiski examination looks for in
+// repeated n-grams. Higher is better.
+pub fn autocorrelation_keysizes(bytes: &[u8], min: usize, max: usize) -> Vec<(usize, f32)> {
+    let scores = (min.max(1)..=max)
+        .filter(|&shift| bytes.len() > shift)
+        .map(|shift| {
+            let matches = bytes.iter()
+                .zip(&bytes[shift..])
+                .filter(|&(a, b)| a == b)
+                .count();
+            (shift, matches as f32 / (bytes.len() - shift) as f32)
+        })
+        .collect();
+    sort_by_score(scores, true)
+}
+
+// The smallest key size whose index of coincidence is within 10% of the
+// best, so we don't pick a multiple of the real key size.
+pub fn guess_keysize(bytes: &[u8], min: usize, max: usize) -> Option<usize> {
+    let ranked = coincidence_keysizes(bytes, min, max);
+    let best = match ranked.first() {
+        Some(&(_, score)) => score,
+        None => return None
+    };
+    ranked.iter()
+        .filter(|&&(_, score)| score >= 0.9 * best)
+        .map(|&(keysize, _)| keysize)
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stats::*;
+
+    #[test]
+    fn test_index_of_coincidence() {
+        assert_eq!(index_of_coincidence(b"aaaa"), 1.0);
+        assert_eq!(index_of_coincidence(b"abcd"), 0.0);
+        assert!(index_of_coincidence(ENGLISH_CORPUS) > 0.05);
+    }
+
+    #[test]
+    fn test_estimators() {
+        let ciphertext = repeating_xor(&ENGLISH_CORPUS[..800], b"Terminator");
+        let top = |ranked: Vec<(usize, f32)>| ranked[0].0 % 10;
+        assert_eq!(top(hamming_keysizes(&ciphertext, 2, 40)), 0);
+        assert_eq!(top(coincidence_keysizes(&ciphertext, 2, 40)), 0);
+        assert_eq!(top(autocorrelation_keysizes(&ciphertext, 2, 40)), 0);
+        assert_eq!(guess_keysize(&ciphertext, 2, 40), Some(10));
+    }
+
+    #[test]
+    fn test_guess_short_keysize() {
+        let ciphertext = repeating_xor(&ENGLISH_CORPUS[..200], b"ICE");
+        assert_eq!(guess_keysize(&ciphertext, 2, 20), Some(3));
+        assert_eq!(guess_keysize(b"x", 2, 20), None);
+    }
+
+    #[test]
+    fn test_zero_keysize() {
+        let ciphertext = repeating_xor(&ENGLISH_CORPUS[..200], b"ICE");
+        for ranked in &[hamming_keysizes(&ciphertext, 0, 5),
+                        coincidence_keysizes(&ciphertext, 0, 5),
+                        autocorrelation_keysizes(&ciphertext, 0, 5)] {
+            assert_eq!(ranked.len(), 5);
+            assert!(ranked.iter().all(|&(keysize, score)| keysize >= 1 && !score.is_nan()));
+        }
+        assert_eq!(guess_keysize(&ciphertext, 0, 0), None);
+    }
+}
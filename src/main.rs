@@ -1,30 +1,65 @@
-#![feature(entry_and_modify)]
 #![feature(inclusive_range_syntax)]
 #![feature(slice_patterns)]
 
 extern crate byteorder;
 extern crate itertools;
 extern crate nalgebra as na;
-#[macro_use]
-extern crate nom;
 extern crate rand;
 
 mod aes;
+// Nothing uses BigUint until the Diffie-Hellman and RSA challenges.
+#[cfg(test)]
+mod bignum;
+mod bitflip;
 mod blockmode;
+// No challenge uses the classical ciphers yet, so they're only built for tests.
+#[cfg(test)]
+mod classical;
+mod clock;
 mod codec;
+mod cookie;
+mod digest;
+mod ecb;
+mod extension;
+mod hmac;
+mod http;
+mod keysize;
+mod md4;
 mod mt;
 mod oracle;
 mod pkcs;
 mod s_box;
+mod sha1;
+mod sha256;
 mod stats;
+mod transcript;
 mod xor;
 
 mod set_1;
 mod set_2;
 mod set_3;
+mod set_4;
+
+use http::*;
+use std::env;
+
+// `cryptopals serve [port]` puts the oracles from the sets on a local HTTP
+// server instead of running the challenges.
+fn serve(port: u16) {
+    let router = set_4::routes(set_3::routes(set_2::routes(Router::new())));
+    let server = Server::spawn_on(port, router).expect("couldn't start server");
+    println!("Serving on http://{}", server.addr());
+    server.run();
+}
 
 fn main() {
+   let args: Vec<String> = env::args().collect();
+   if args.get(1).map(|arg| arg == "serve").unwrap_or(false) {
+       serve(args.get(2).and_then(|port| port.parse().ok()).unwrap_or(0));
+       return;
+   }
    set_1::set_1();
    set_2::set_2();
    set_3::set_3();
+   set_4::set_4();
 }
@@ -0,0 +1,99 @@
+use digest::*;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+const H0: [u32; 4] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476];
+
+// RFC 1320. Same shape as SHA-1, but little-endian throughout.
+#[derive(Debug, Clone)]
+pub struct Md4Compress;
+
+pub type Md4 = MdHash<Md4Compress>;
+
+impl Compress for Md4Compress {
+    fn initial_state() -> Vec<u32> {
+        H0.to_vec()
+    }
+
+    fn big_endian() -> bool {
+        false
+    }
+
+    fn compress(state: &mut [u32], block: &[u8]) {
+        let mut x = [0u32; 16];
+        LittleEndian::read_u32_into(block, &mut x);
+
+        let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
+        let g = |x: u32, y: u32, z: u32| (x & y) | (x & z) | (y & z);
+        let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+
+        let (mut a, mut b, mut c, mut d) = (state[0], state[1], state[2], state[3]);
+
+        for &i in &[0, 4, 8, 12] {
+            a = a.wrapping_add(f(b, c, d)).wrapping_add(x[i]).rotate_left(3);
+            d = d.wrapping_add(f(a, b, c)).wrapping_add(x[i + 1]).rotate_left(7);
+            c = c.wrapping_add(f(d, a, b)).wrapping_add(x[i + 2]).rotate_left(11);
+            b = b.wrapping_add(f(c, d, a)).wrapping_add(x[i + 3]).rotate_left(19);
+        }
+
+        for &i in &[0, 1, 2, 3] {
+            let k = 0x5A827999;
+            a = a.wrapping_add(g(b, c, d)).wrapping_add(x[i]).wrapping_add(k).rotate_left(3);
+            d = d.wrapping_add(g(a, b, c)).wrapping_add(x[i + 4]).wrapping_add(k).rotate_left(5);
+            c = c.wrapping_add(g(d, a, b)).wrapping_add(x[i + 8]).wrapping_add(k).rotate_left(9);
+            b = b.wrapping_add(g(c, d, a)).wrapping_add(x[i + 12]).wrapping_add(k).rotate_left(13);
+        }
+
+        for &i in &[0, 2, 1, 3] {
+            let k = 0x6ED9EBA1;
+            a = a.wrapping_add(h(b, c, d)).wrapping_add(x[i]).wrapping_add(k).rotate_left(3);
+            d = d.wrapping_add(h(a, b, c)).wrapping_add(x[i + 8]).wrapping_add(k).rotate_left(9);
+            c = c.wrapping_add(h(d, a, b)).wrapping_add(x[i + 4]).wrapping_add(k).rotate_left(11);
+            b = b.wrapping_add(h(c, d, a)).wrapping_add(x[i + 12]).wrapping_add(k).rotate_left(15);
+        }
+
+        for (s, v) in state.iter_mut().zip(&[a, b, c, d]) {
+            *s = s.wrapping_add(*v);
+        }
+    }
+}
+
+pub fn md4(data: &[u8]) -> Vec<u8> {
+    Md4::digest(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::*;
+
+    #[test]
+    fn test_md4_vectors() {
+        let vectors: &[(&[u8], &[u8])] = &[
+            (b"", b"31d6cfe0d16ae931b73c59d7e0c089c0"),
+            (b"a", b"bde52cb31de33e46245e05fbdbd6fb24"),
+            (b"abc", b"a448017aaf21d8525fc10ae87aa6729d"),
+            (b"message digest", b"d9130a8164549fe818874806e1c7014b"),
+            (b"abcdefghijklmnopqrstuvwxyz", b"d79e1c308aa5bbcdeea8ed63df412da9"),
+            (b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+             b"043f8582f241db351ce627e153e7f0e4"),
+            (b"1234567890123456789012345678901234567890\
+               1234567890123456789012345678901234567890",
+             b"e33b4ddc9c38f2199c3e7b164fcc0536")
+        ];
+        for &(input, expected) in vectors {
+            assert_eq!(base16_encode(&md4(input)), expected.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_md4_from_digest() {
+        let message = b"message digest";
+        let mut glued = message.to_vec();
+        glued.extend(Md4::padding(message.len() as u64));
+        let mut hasher = Md4::from_digest(&md4(message), glued.len() as u64);
+        glued.extend(b" and then some");
+        hasher.update(b" and then some");
+        assert_eq!(hasher.finalize(), md4(&glued));
+    }
+}
@@ -1,3 +1,7 @@
+use std::cmp::min;
+use std::sync::Arc;
+use std::thread;
+
 pub struct MersenneTwister {
     pointer: usize,
     state: [u32; 624]
@@ -92,6 +96,57 @@ impl MersenneTwister {
     }
 }
 
+fn seed_matches(seed: u32, outputs: &[(usize, u32)]) -> bool {
+    let mut mt = MersenneTwister::new(seed);
+    let mut pos = 0;
+    for &(target_pos, target) in outputs {
+        while pos < target_pos {
+            mt.next();
+            pos += 1;
+        }
+        if mt.next() != target {
+            return false;
+        }
+        pos += 1;
+    }
+    true
+}
+
+// Find every seed in start..=finish that could have produced `outputs`.
+// Each output is paired with its position in the stream, 0 being the result
+// of the first call to `next`, so they don't need to be consecutive or start
+// at the beginning. The range is split into windows that are searched on
+// separate threads.
+pub fn crack_seed(outputs: &[(usize, u32)], start: u32, finish: u32) -> Vec<u32> {
+    let threads = 4;
+    if start > finish {
+        return Vec::new();
+    }
+    let mut sorted = outputs.to_vec();
+    sorted.sort();
+    let outputs = Arc::new(sorted);
+
+    let (start, finish) = (u64::from(start), u64::from(finish));
+    let width = (finish - start) / threads + 1;
+    let handles: Vec<_> = (0..threads).map(|t| {
+        let outputs = outputs.clone();
+        let lo = start + t * width;
+        let hi = min(lo + width, finish + 1);
+        thread::spawn(move || {
+            (lo..hi)
+                .map(|seed| seed as u32)
+                .filter(|&seed| seed_matches(seed, &outputs))
+                .collect::<Vec<u32>>()
+        })
+    }).collect();
+
+    let mut candidates = Vec::new();
+    for handle in handles {
+        candidates.extend(handle.join().expect("Seed search thread panicked"));
+    }
+    candidates
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -107,4 +162,16 @@ mod tests {
         let thousandth_out = mt.next();
         assert_eq!(thousandth_out, 3043451800);
     }
+
+    #[test]
+    fn test_crack_seed() {
+        let mut mt = MersenneTwister::new(31337);
+        let outputs: Vec<(usize, u32)> = (0..700)
+            .map(|pos| (pos, mt.next()))
+            .filter(|&(pos, _)| pos == 3 || pos == 650)
+            .collect();
+        assert_eq!(crack_seed(&outputs, 30000, 33000), vec![31337]);
+        assert_eq!(crack_seed(&outputs[1..], 31337, 31337), vec![31337]);
+        assert!(crack_seed(&outputs, 0, 1000).is_empty());
+    }
 }
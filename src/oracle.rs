@@ -1,68 +1,427 @@
 use aes::*;
 use blockmode::*;
+use codec::*;
 use rand;
+use rand::Rng;
 use rand::distributions::{IndependentSample, Range};
-use std::collections::HashSet;
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::io::Write;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     ECB,
-    CBC
+    CBC,
+    CTR,
+    CFB,
+    OFB
 }
 
-fn random_pad(bytes: &[u8]) -> Vec<u8> {
-    let five_to_ten = Range::new(5usize, 10);
-    let mut rng = rand::thread_rng();
-    let pre = five_to_ten.ind_sample(&mut rng);
-    let post = five_to_ten.ind_sample(&mut rng);
+pub const ALL_MODES: &[Mode] = &[Mode::ECB, Mode::CBC, Mode::CTR, Mode::CFB, Mode::OFB];
 
-    let total_length = pre + bytes.len() + post;
+// How many random bytes go either side of the plaintext: a length is drawn
+// from [min, max) for each side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Padding {
+    pub prefix: (usize, usize),
+    pub suffix: (usize, usize)
+}
 
-    let mut buffer = Vec::with_capacity(total_length);
+// What challenge 11 asks for.
+pub const FIVE_TO_TEN: Padding = Padding { prefix: (5, 10), suffix: (5, 10) };
 
-    for _ in 0..pre {
-        buffer.push(rand::random());
-    }
-    
+fn random_bytes(range: (usize, usize)) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    let len = if range.0 < range.1 {
+        Range::new(range.0, range.1).ind_sample(&mut rng)
+    } else {
+        range.0
+    };
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+fn random_pad(bytes: &[u8], padding: Padding) -> Vec<u8> {
+    let mut buffer = random_bytes(padding.prefix);
     buffer.extend(bytes);
-    
-    for _ in 0..post {
-        buffer.push(rand::random());
-    }
-    
+    buffer.extend(random_bytes(padding.suffix));
     buffer
 }
 
+// Encrypts under any of the modes. For CTR, the first 8 bytes of the IV are
+// the nonce.
+pub fn encrypt_with_mode(mode: Mode, pt: &[u8], key: &[u8; 16], iv: &[u8]) -> Vec<u8> {
+    match mode {
+        Mode::ECB => ecb_encrypt(AES128, pt, key),
+        Mode::CBC => cbc_encrypt(AES128, pt, key, iv),
+        Mode::CTR => {
+            let mut nonce = [0; 8];
+            nonce.copy_from_slice(&iv[..8]);
+            ctr_encrypt(AES128, pt, *key, nonce)
+        },
+        Mode::CFB => cfb_encrypt(AES128, pt, key, iv),
+        Mode::OFB => ofb_encrypt(AES128, pt, key, iv)
+    }
+}
+
 pub fn random_encrypt(plaintext: &[u8]) -> (Vec<u8>, Mode) {
+    random_encrypt_with(plaintext, &[Mode::ECB, Mode::CBC], FIVE_TO_TEN)
+}
+
+pub fn random_encrypt_with(plaintext: &[u8], modes: &[Mode], padding: Padding)
+    -> (Vec<u8>, Mode) {
     let random_key: [u8; 16] = rand::random();
-    let padded = random_pad(plaintext);
     let iv: [u8; 16] = rand::random();
-    if rand::random() {
-        (ecb_encrypt(AES128, &padded, &random_key),      Mode::ECB)
+    let mode = *rand::thread_rng().choose(modes).expect("no modes to choose from");
+    let padded = random_pad(plaintext, padding);
+    (encrypt_with_mode(mode, &padded, &random_key, &iv), mode)
+}
+
+// Under CBC it's vanishingly unlikely to get any duplicated blocks, so if we
+// find any, ECB must have been used. However, there must be duplicates in the
+// plaintext in order to get duplicates in the ciphertext, so long plaintexts
+// give better results.
+pub fn ecb_cbc_oracle(bytes: &[u8]) -> Mode {
+    if detect_ecb(bytes, 16).probability > 0.5 {
+        Mode::ECB
     } else {
-        (cbc_encrypt(AES128, &padded, &random_key, &iv), Mode::CBC)
+        Mode::CBC
     }
 }
 
-// Check for any duplicated 16-byte chunks in a ciphertext.
-// It's vanishingly unlikely to get any under CBC, so if we find any, ECB must 
-// have been used. However, there must be duplicates in the plaintext in order
-// to get duplicates in the ciphertext, so long plaintexts give better results.
-pub fn ecb_cbc_oracle(bytes: &[u8]) -> Mode {
-    let mut found_dupes = false;
-    for skip_amt in 0..16 {
-        let bytes = &bytes[skip_amt..];
-        let mut seen = HashSet::new();
-        for chunk in bytes.chunks(16) {
-            if !seen.insert(chunk) {
-                found_dupes = true;
+// A chosen-plaintext oracle that also lets the caller pick the IV, as some
+// badly designed APIs do. Takes (iv, plaintext).
+pub type ModeOracle = dyn Fn(&[u8], &[u8]) -> Vec<u8>;
+
+// Builds an oracle with a fixed random key, mode, prefix and suffix. The mode
+// is returned so the answer can be checked.
+pub fn random_mode_oracle(modes: &[Mode], padding: Padding) -> (Box<ModeOracle>, Mode) {
+    let key: [u8; 16] = rand::random();
+    let mode = *rand::thread_rng().choose(modes).expect("no modes to choose from");
+    let prefix = random_bytes(padding.prefix);
+    let suffix = random_bytes(padding.suffix);
+    let oracle = move |iv: &[u8], pt: &[u8]| {
+        let mut buffer = prefix.clone();
+        buffer.extend(pt);
+        buffer.extend(&suffix);
+        encrypt_with_mode(mode, &buffer, &key, iv)
+    };
+    (Box::new(oracle), mode)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Detection {
+    pub mode: Mode,
+    pub queries: usize
+}
+
+// Tells the five modes apart:
+//  - adding a byte grows a block mode's output by 0 or 16, a stream mode's by 1
+//  - ECB repeats blocks when fed repeated plaintext, CBC doesn't
+//  - changing one plaintext byte changes one ciphertext byte under CTR and
+//    OFB, but garbles the following block under CFB
+//  - OFB's keystream is the IV encrypted repeatedly, so feeding a keystream
+//    block back in as the IV shifts the keystream along by a block. CTR's
+//    keystream has no such relationship with its nonce.
+pub fn detect_mode(oracle: &ModeOracle) -> Detection {
+    let queries = Cell::new(0);
+    let query = |iv: &[u8], pt: &[u8]| {
+        queries.set(queries.get() + 1);
+        oracle(iv, pt)
+    };
+    let iv = [0; 16];
+
+    let empty = query(&iv, b"").len();
+    let one = query(&iv, b"A").len();
+    let mode = if one != empty + 1 {
+        if detect_ecb(&query(&iv, &[b'A'; 48]), 16).repeats > 0 {
+            Mode::ECB
+        } else {
+            Mode::CBC
+        }
+    } else {
+        let a = query(&iv, &[b'A'; 32]);
+        let mut flipped = [b'A'; 32];
+        flipped[0] = b'B';
+        let b = query(&iv, &flipped);
+        let diffs: Vec<usize> = (0..a.len()).filter(|&i| a[i] != b[i]).collect();
+        if diffs.len() > 1 {
+            Mode::CFB
+        } else {
+            // The first changed byte is where our input starts. Make the
+            // input long enough that block 2f + 1 is still ours, where f is
+            // the first whole block of it.
+            let start = diffs[0];
+            let first = (start + 15) / 16;
+            let pt = vec![b'A'; start + 64];
+            let ct = query(&iv, &pt);
+            let block = &ct[first * 16..(first + 1) * 16];
+            let keystream: Vec<u8> = block.iter().map(|b| b ^ b'A').collect();
+            let shifted = query(&keystream, &pt);
+            let next = (2 * first + 1) * 16;
+            if shifted[first * 16..(first + 1) * 16] == ct[next..next + 16] {
+                Mode::OFB
+            } else {
+                Mode::CTR
             }
         }
+    };
+
+    Detection { mode: mode, queries: queries.get() }
+}
+
+pub fn decrypt_with_mode(mode: Mode, ct: &[u8], key: &[u8; 16], iv: &[u8])
+    -> Result<Vec<u8>, String> {
+    match mode {
+        Mode::ECB => ecb_decrypt(AES128, ct, key).map_err(|e| e.to_string()),
+        Mode::CBC => cbc_decrypt(AES128, ct, key, iv).map_err(|e| e.to_string()),
+        Mode::CTR | Mode::OFB => Ok(encrypt_with_mode(mode, ct, key, iv)),
+        Mode::CFB => Ok(cfb_decrypt(AES128, ct, key, iv))
+    }
+}
+
+// The three kinds of oracle the attacks are written against. Plain closures
+// work as oracles too.
+pub trait EncryptionOracle {
+    fn encrypt(&self, pt: &[u8]) -> Vec<u8>;
+}
+
+pub trait DecryptionOracle {
+    fn decrypt(&self, ct: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+// Only says whether the ciphertext decrypted with valid padding.
+pub trait PaddingOracle {
+    fn padding_ok(&self, ct: &[u8]) -> bool;
+}
+
+impl<F> EncryptionOracle for F where F: Fn(&[u8]) -> Vec<u8> {
+    fn encrypt(&self, pt: &[u8]) -> Vec<u8> {
+        self(pt)
+    }
+}
+
+impl<F> DecryptionOracle for F where F: Fn(&[u8]) -> Result<Vec<u8>, String> {
+    fn decrypt(&self, ct: &[u8]) -> Result<Vec<u8>, String> {
+        self(ct)
+    }
+}
+
+impl<F> PaddingOracle for F where F: Fn(&[u8]) -> bool {
+    fn padding_ok(&self, ct: &[u8]) -> bool {
+        self(ct)
+    }
+}
+
+// Encrypts prefix || input || suffix under a fixed key and IV. Everything is
+// random unless set otherwise.
+#[derive(Debug, Clone)]
+pub struct Oracle {
+    mode: Mode,
+    key: [u8; 16],
+    iv: [u8; 16],
+    prefix: Vec<u8>,
+    suffix: Vec<u8>
+}
+
+impl Oracle {
+    pub fn new(mode: Mode) -> Self {
+        Oracle {
+            mode: mode,
+            key: rand::random(),
+            iv: rand::random(),
+            prefix: Vec::new(),
+            suffix: Vec::new()
+        }
     }
 
-    if found_dupes {
-        Mode::ECB
-    } else {
-        Mode::CBC
+    pub fn with_key(mut self, key: [u8; 16]) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn with_iv(mut self, iv: [u8; 16]) -> Self {
+        self.iv = iv;
+        self
+    }
+
+    pub fn with_prefix(mut self, prefix: &[u8]) -> Self {
+        self.prefix = prefix.to_vec();
+        self
+    }
+
+    // A prefix of random bytes, of a length drawn once from [min, max).
+    pub fn with_random_prefix(mut self, min: usize, max: usize) -> Self {
+        self.prefix = random_bytes((min, max));
+        self
+    }
+
+    pub fn with_suffix(mut self, suffix: &[u8]) -> Self {
+        self.suffix = suffix.to_vec();
+        self
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn key(&self) -> [u8; 16] {
+        self.key
+    }
+
+    pub fn iv(&self) -> [u8; 16] {
+        self.iv
+    }
+}
+
+impl EncryptionOracle for Oracle {
+    fn encrypt(&self, pt: &[u8]) -> Vec<u8> {
+        let mut buffer = self.prefix.clone();
+        buffer.extend(pt);
+        buffer.extend(&self.suffix);
+        encrypt_with_mode(self.mode, &buffer, &self.key, &self.iv)
+    }
+}
+
+impl DecryptionOracle for Oracle {
+    fn decrypt(&self, ct: &[u8]) -> Result<Vec<u8>, String> {
+        decrypt_with_mode(self.mode, ct, &self.key, &self.iv)
+    }
+}
+
+impl PaddingOracle for Oracle {
+    fn padding_ok(&self, ct: &[u8]) -> bool {
+        self.decrypt(ct).is_ok()
+    }
+}
+
+// Wraps any oracle and counts the queries made of it. Nothing is written
+// unless a log is asked for with `logged` (stdout) or `logged_to`.
+pub struct Counted<O> {
+    inner: O,
+    queries: Cell<usize>,
+    log: Option<RefCell<Box<dyn Write>>>
+}
+
+impl<O> Counted<O> {
+    pub fn new(inner: O) -> Self {
+        Counted { inner: inner, queries: Cell::new(0), log: None }
+    }
+
+    pub fn logged(self) -> Self {
+        self.logged_to(io::stdout())
+    }
+
+    pub fn logged_to<W>(mut self, writer: W) -> Self where W: Write + 'static {
+        self.log = Some(RefCell::new(Box::new(writer)));
+        self
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queries.get()
+    }
+
+    pub fn inner(&self) -> &O {
+        &self.inner
+    }
+
+    fn record(&self, input: &[u8], output: &[u8]) {
+        self.queries.set(self.queries.get() + 1);
+        if let Some(ref log) = self.log {
+            // A log that can't be written to shouldn't stop the attack.
+            let _ = writeln!(log.borrow_mut(), "query {}: {} -> {}", self.queries.get(),
+                             String::from_utf8_lossy(&base16_encode(input)),
+                             String::from_utf8_lossy(output));
+        }
+    }
+}
+
+impl<O> EncryptionOracle for Counted<O> where O: EncryptionOracle {
+    fn encrypt(&self, pt: &[u8]) -> Vec<u8> {
+        let ct = self.inner.encrypt(pt);
+        self.record(pt, &base16_encode(&ct));
+        ct
+    }
+}
+
+impl<O> DecryptionOracle for Counted<O> where O: DecryptionOracle {
+    fn decrypt(&self, ct: &[u8]) -> Result<Vec<u8>, String> {
+        let pt = self.inner.decrypt(ct);
+        match pt {
+            Ok(ref pt) => self.record(ct, &base16_encode(pt)),
+            Err(ref e) => self.record(ct, e.as_bytes())
+        }
+        pt
+    }
+}
+
+impl<O> PaddingOracle for Counted<O> where O: PaddingOracle {
+    fn padding_ok(&self, ct: &[u8]) -> bool {
+        let ok = self.inner.padding_ok(ct);
+        self.record(ct, if ok { b"ok" } else { b"bad padding" });
+        ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_mode() {
+        let padding = Padding { prefix: (0, 40), suffix: (0, 20) };
+        for &mode in ALL_MODES {
+            for _ in 0..5 {
+                let (oracle, _) = random_mode_oracle(&[mode], padding);
+                let detection = detect_mode(&*oracle);
+                assert_eq!(detection.mode, mode);
+                assert!(detection.queries <= 6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_encrypt_with() {
+        let padding = Padding { prefix: (3, 3), suffix: (0, 0) };
+        let (ct, mode) = random_encrypt_with(b"hello", &[Mode::CTR, Mode::OFB], padding);
+        assert!(mode == Mode::CTR || mode == Mode::OFB);
+        assert_eq!(ct.len(), 8);
+    }
+
+    #[test]
+    fn test_oracle() {
+        let oracle = Oracle::new(Mode::ECB).with_prefix(b"before").with_suffix(b"after");
+        let ct = oracle.encrypt(b"during");
+        assert_eq!(ct.len(), 32);
+        assert_eq!(oracle.decrypt(&ct).unwrap(), b"beforeduringafter".to_vec());
+        assert!(oracle.padding_ok(&ct));
+
+        let oracle = Oracle::new(Mode::CBC).with_random_prefix(0, 32);
+        let ct = oracle.encrypt(b"");
+        assert_eq!(oracle.encrypt(b""), ct);
+        assert!(ct.len() == 16 || ct.len() == 32);
+        let mut bad = ct.clone();
+        let last = bad.len() - 1;
+        bad[last] ^= 1;
+        assert!(!oracle.padding_ok(&bad));
+
+        for &mode in ALL_MODES {
+            let oracle = Oracle::new(mode).with_suffix(b"!");
+            let ct = oracle.encrypt(b"round trip");
+            assert_eq!(oracle.decrypt(&ct).unwrap(), b"round trip!".to_vec());
+        }
+    }
+
+    #[test]
+    fn test_counted() {
+        let counted = Counted::new(|pt: &[u8]| pt.to_vec()).logged_to(io::sink());
+        assert_eq!(counted.encrypt(b"abc"), b"abc".to_vec());
+        counted.encrypt(b"");
+        assert_eq!(counted.queries(), 2);
+
+        let counted = Counted::new(Oracle::new(Mode::CBC));
+        let ct = counted.inner().encrypt(b"not counted");
+        assert!(counted.padding_ok(&ct));
+        assert!(counted.decrypt(&ct).is_ok());
+        assert_eq!(counted.queries(), 2);
     }
 }
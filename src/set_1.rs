@@ -1,16 +1,13 @@
 use aes::*;
 use blockmode::*;
 use codec::*;
+use keysize::*;
 use stats::*;
 use xor::*;
 
-use std::cmp::Ordering;
-use std::f32::INFINITY;
 use std::fs::File;
 use std::io::prelude::*;
-use std::io::BufReader;
 use std::str::from_utf8;
-use itertools::Itertools;
 
 pub fn set_1() {
     println!("Set 1");
@@ -29,7 +26,7 @@ fn _1() {
     let mut contents = String::new();
     file.read_to_string(&mut contents).expect("Couldn't read to string");
     let contents = contents.trim().as_bytes();
-    let bytes = base16_decode_filter(contents);
+    let bytes = base16_decode_filter(contents).unwrap();
     let base64enc = base64_encode(bytes.as_slice());
 
     assert_eq!(
@@ -43,8 +40,8 @@ fn _2() {
     let res  = b"746865206b696420646f6e277420706c6179";
 
     let ans = fixed_xor(
-        &base16_decode_filter(xor1),
-        &base16_decode_filter(xor2)
+        &base16_decode_filter(xor1).unwrap(),
+        &base16_decode_filter(xor2).unwrap()
     ).unwrap();
 
     assert_eq!(base16_encode(&ans)[..], res[..]);
@@ -52,7 +49,7 @@ fn _2() {
 
 fn _3() {
     let code = b"1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736";
-    let bytes = base16_decode_filter(code);
+    let bytes = base16_decode_filter(code).unwrap();
     let ans = decrypt_single_byte_xor(&bytes).0;
 
     assert_eq!("Cooking MC's like a pound of bacon",
@@ -60,25 +57,15 @@ fn _3() {
 }
 
 fn _4() {
-    let file = File::open("data/4.txt").expect("no 4.txt");
-    let buf_reader = BufReader::new(file);
-    let l = buf_reader.lines();
-    let mut best_rated = (INFINITY, String::new());
-    for line in l {
-        let bytes = base16_decode_filter(line.expect("no line").as_bytes());
-        let res = decrypt_single_byte_xor(&bytes).0;
-        let this_score = score(&res);
-        if let Ok(string) = from_utf8(&res) {
-            let printable: Vec<u8> = string.bytes()
-                .filter(|ch| !char::is_control(*ch as char)).collect();
-            if this_score < best_rated.0 {
-                best_rated =
-                    (this_score, from_utf8(&printable).unwrap().to_string());
-            }
-        }
-    }
-
-    assert_eq!(best_rated.1, "nOWTHATTHEPARTYISJUMPING*");
+    let lines: Vec<Vec<u8>> = include_str!("../data/4.txt")
+        .lines()
+        .map(|l| base16_decode_filter(l.as_bytes()).unwrap())
+        .collect();
+    let best = &find_single_byte_xor(&lines, &ChiSquared::english(), 1)[0];
+
+    assert_eq!(best.line, 171);
+    assert_eq!(best.key, 0x35);
+    assert_eq!(from_utf8(&best.plaintext).unwrap(), "Now that the party is jumping\n");
 }
 
 fn _5() {
@@ -88,7 +75,7 @@ fn _5() {
     let enc = repeating_xor(raw, key);
     let expected = include_bytes!("../data/5_result.txt");
 
-    assert_eq!(enc, base16_decode_filter(expected));
+    assert_eq!(enc, base16_decode_filter(expected).unwrap());
 
     let dec = repeating_xor(&enc, key);
 
@@ -100,34 +87,13 @@ fn _6() {
     let mut base64_bytes = Vec::new();
     file.read_to_end(&mut base64_bytes).unwrap();
     let bytes = base64_decode_filter(&base64_bytes);
-    let mut key_scores = Vec::<(usize, f64)>::new();
-    for keysize in 2..40 {
-        let f = &bytes[0..keysize];
-        let s = &bytes[keysize..keysize * 2];
-        let dist = hamming(f, s).expect("no hamming") as f64 / keysize as f64;
-        key_scores.push((keysize, dist));
-    }
-    key_scores.sort_by(|&a, &b| float_cmp(a.1, b.1));
-
-    let mut results: Vec<Vec<u8>> = Vec::new();
-    for (keysize, _) in key_scores {
-        let bytes_t = transpose(&bytes, keysize);
-        let the_key: Vec<u8> =
-            bytes_t
-                .iter()
-                .map(|block| decrypt_single_byte_xor(block).1)
-                .collect();
-
-        let dec: Vec<u8> = repeating_xor(&bytes, &the_key)
-                .into_iter().filter(|ch| !char::is_control(*ch as char)).collect();
-
-        results.push(dec);
-    }
-    results.sort_by(|x, y| float_cmp(f64::from(score(x)), f64::from(score(y))));
-
-    let answer = from_utf8(&results[0]).unwrap();
-
-    assert_eq!(answer, include_str!("../data/6_result.txt"));
+
+    let keysize = guess_keysize(&bytes, 2, 40).expect("no keysize");
+    let key = break_repeating_xor(&bytes, keysize, &ChiSquared::english());
+    assert_eq!(from_utf8(&key).unwrap(), "Terminator X: Bring the noise");
+
+    let dec = repeating_xor(&bytes, &key);
+    assert_eq!(dec[..], include_bytes!("../data/7_result.txt")[..]);
 }
 
 fn _7() {
@@ -141,35 +107,14 @@ fn _7() {
 fn _8() {
     let raw: Vec<Vec<u8>> = include_str!("../data/8.txt")
         .lines()
-        .map(|l| base16_decode_filter(l.as_bytes()))
+        .map(|l| base16_decode_filter(l.as_bytes()).unwrap())
         .collect();
 
-    let mut dupes = false;
-    for enc in &raw {
-        let analysis = detect_ecb(enc);
-        for (_, v) in analysis {
-            if v > 1 {
-                dupes = true;
-            }
-        }
-    }
-    assert!(dupes);
-}
-
-fn float_cmp(a: f64, b: f64) -> Ordering {
-    a.partial_cmp(&b).expect("Some arguments to float_cmp weren't finite")
-}
+    let ranked = rank_ecb(&raw, 16);
+    let (line_ix, ref report) = ranked[0];
 
-fn transpose(s: &[u8], width: usize) -> Vec<Vec<u8>> {
-    let mut buffer = Vec::new();
-    for i in 0..width {
-        let chunk: Vec<u8> = s
-            .iter()
-            .skip(i)
-            .step(width)
-            .cloned()
-            .collect();
-        buffer.push(chunk);
-    }
-    buffer
+    assert_eq!(line_ix + 1, 133);
+    assert_eq!(report.repeats, 3);
+    assert!(report.probability > 0.99);
+    assert!(ranked[1].1.probability < 0.5);
 }
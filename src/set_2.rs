@@ -1,12 +1,13 @@
 use aes::*;
+use bitflip::*;
 use blockmode::*;
 use codec::*;
+use cookie::*;
+use ecb::*;
+use http::*;
 use oracle::*;
 use pkcs::*;
 use rand;
-use xor::*;
-
-use nom::IResult::*;
 
 use std::str::from_utf8;
 
@@ -44,29 +45,9 @@ fn _11() {
     }
 }
 
-type Oracle = Fn(&[u8]) -> Vec<u8>;
-
-fn ecb_block_size(ora: &Oracle) -> usize {
-    let bytes = &[b'A'; 255];
-    let mut out_length = None;
-    let mut block_size = None;
-    for i in 1..=255 {
-        let enc = ora(&bytes[..i]);
-        match out_length {
-            Some(l) if enc.len() > l => {
-                block_size = Some(enc.len() - l);
-                break;
-            }
-            Some(_) => { },
-            None => { out_length = Some(enc.len()); }
-        }
-    }
-    block_size.unwrap()
-}
-
-fn is_ecb(ora: &Oracle, block_size: usize) -> bool {
+fn is_ecb<O>(ora: &O, block_size: usize) -> bool where O: EncryptionOracle {
     let ecb_test = vec![b'A'; block_size * 2];
-    let ecb_test_out = ora(&ecb_test);
+    let ecb_test_out = ora.encrypt(&ecb_test);
     ecb_test_out[..block_size] == ecb_test_out[block_size..block_size * 2]
 }
 
@@ -87,7 +68,8 @@ fn bytes_by_english_freq() -> Vec<u8> {
 // unnecessary.
 // Otherwise, start_block gets the index of the first block of text we
 // have full control over.
-fn break_ecb_with_oracle(ora: &Oracle, block_size: usize, prefix_length: usize) -> Vec<u8> {
+fn break_ecb_with_oracle<O>(ora: &O, block_size: usize, prefix_length: usize) -> Vec<u8>
+    where O: EncryptionOracle {
     // We want to always pad with this many bytes no matter what
     // so that the rest is block-aligned.
     let const_pad = block_size - (prefix_length % block_size);
@@ -109,7 +91,7 @@ fn break_ecb_with_oracle(ora: &Oracle, block_size: usize, prefix_length: usize)
             let mut padding = vec![b'A'; pad_width + const_pad];
 
             // Get the actual value of the block.
-            let actual = &ora(&padding)[
+            let actual = &ora.encrypt(&padding)[
                 block_size * block_ix..
                 block_size * (block_ix + 1)
             ];
@@ -133,7 +115,7 @@ fn break_ecb_with_oracle(ora: &Oracle, block_size: usize, prefix_length: usize)
             let mut matched = false;
             for last_byte in &freq {
                 dict_padding[const_pad + block_size - 1] = *last_byte;
-                let this_option = &ora(&dict_padding)[
+                let this_option = &ora.encrypt(&dict_padding)[
                     block_size * start_block..
                     block_size * (start_block + 1)
                 ];
@@ -153,18 +135,11 @@ fn break_ecb_with_oracle(ora: &Oracle, block_size: usize, prefix_length: usize)
 }
 
 fn _12() {
-    // Get block size
-    let key: [u8; 16] = rand::random();
-
     let unknown = base64_decode_filter(
         include_bytes!("../data/12.txt"));
+    let ora = &Oracle::new(Mode::ECB).with_suffix(&unknown);
 
-    let ora = &(move |buffer: &[u8]| {
-        let mut v = Vec::new();
-        v.extend_from_slice(buffer);
-        v.extend_from_slice(&unknown);
-        ecb_encrypt(AES128, &v, &key)
-    });
+    // Get block size
     let block_size = ecb_block_size(ora);
 
     // Detect that oracle uses ecb
@@ -175,152 +150,42 @@ fn _12() {
     println!("{}", from_utf8(&answer).unwrap());
 }
 
-type KVVec<'a, 'b> = Vec<(&'a [u8], &'b [u8])>;
-
-named!(key,
-    take_until_and_consume!("="));
-named!(value,
-    take_until_and_consume!("&"));
-named!(kvpair< (&[u8], &[u8]) >,
-    tuple!(key, value));
-named!(kvs<KVVec>, many0!(kvpair));
-
-fn sanitize(bytes: &[u8]) -> Vec<u8> {
-    bytes.iter()
-        .cloned()
-        .filter(|&ch| ch != b'=' && ch != b'&')
-        .collect()
+fn mk_encrypted_url_profile(email: &[u8], key: [u8; 16]) -> Vec<u8> {
+    ecb_encrypt(AES128, &profile_for(email), &key)
 }
 
-fn mk_profile(email: &[u8]) -> KVVec {
-    let mut h: KVVec = Vec::new();
-    h.push((b"email", email));
-    h.push((b"uid",   b"10"));
-    h.push((b"role",  b"user"));
-    h
+fn profile_handler(key: [u8; 16]) -> Handler {
+    encryption_handler(move |email: &[u8]| mk_encrypted_url_profile(email, key))
 }
 
-fn url_encode(obj: KVVec) -> Vec<u8> {
-    let mut out = Vec::new();
-    for (k, v) in obj {
-        out.extend_from_slice(k);
-        out.push(b'=');
-        out.extend_from_slice(v);
-        out.push(b'&');
-    }
-    out.pop();
-    out
-}
-
-fn mk_encrypted_url_profile(email: &[u8], key: [u8; 16]) -> Vec<u8> {
-    let obj = mk_profile(email);
-    let url = url_encode(obj);
-    ecb_encrypt(AES128, &url, &key)
+// What the server would do with the cookie: decrypt it and look at the role.
+fn is_admin_profile(ct: &[u8], key: [u8; 16]) -> bool {
+    ecb_decrypt(AES128, ct, &key).ok()
+        .and_then(|dec| Cookie::parse(&dec, b'&').ok())
+        .map(|profile| profile.get(b"role") == Some(&b"admin"[..]))
+        .unwrap_or(false)
 }
 
-fn pretty_ct(ciphertext: &[u8]) {
-    for chunk in ciphertext.chunks(16) {
-        print!("{} ", from_utf8(&base16_encode(chunk)).unwrap());
-    }
-    println!();
+// GET /profile?input=<hex email> gives the encrypted profile.
+pub fn routes(router: Router) -> Router {
+    router.route("/profile", profile_handler(rand::random()))
 }
 
-// Everything here is hardcoded because I'm lazy but you can do this even if
-// you don't know the prefix (which in this case is `email=`) and you could
-// decode the entire postfix to determine that the padding on `to` needs to
-// be 13
+// The profile encoder escapes `&` and `=`, but not the padding bytes, so we
+// can get "admin" plus its PKCS#7 padding into a block of its own and paste
+// it over the end of a profile that ends in "role=user".
 fn _13() {
     let key = rand::random();
-    let oracle = &(move |bytes: &[u8]| {
-        let mut v = Vec::new();
-        let sanitized = sanitize(bytes);
-        v.extend_from_slice(&sanitized);
-        mk_encrypted_url_profile(&v, key)
-    });
-
-    let block_size = ecb_block_size(oracle);
-    let prefix_length = guess_prefix_length(oracle, block_size);
-
-    let pad_length = block_size - (prefix_length % block_size);
-    let pad_blocks = 1 + (prefix_length / block_size);
-
-    // We're setting our email address such that one of the blocks of the
-    // ciphertext will be "admin", padded using EKCS7.
-    // Since we computed the prefix length, we know exactly which
-    // ciphertext block that is.
-    let mut evil_email = vec![b'A'; pad_length];
-    evil_email.extend_from_slice(
-        b"admin\x0b\x0b\x0b\x0b\x0b\x0b\x0b\x0b\x0b\x0b\x0b"
-    );
-    let evil = oracle(&evil_email);
-
-    pretty_ct(&evil);
-
-    // We're setting our email address such that the last block of the
-    // ciphertext will be "user____________" (EKCS7 padded) as in "role=user"
-    // Since we just figured out the ciphertext of "admin___________" (EKCS7
-    // padded), we can just paste it over that last block and the result will
-    // decrypt successfully.
-    let mut to = oracle(&[b'A'; 13][..]);
-    let last = to.len() - 16;
-    to[last..].copy_from_slice(
-        &evil[
-            pad_blocks      * block_size..
-            (pad_blocks + 1)* block_size
-        ]
-    );
+    let oracle = &(move |bytes: &[u8]| mk_encrypted_url_profile(bytes, key));
 
-    pretty_ct(&to);
+    let forged = cut_and_paste(oracle, b"user", b"admin", |ct: &[u8]| is_admin_profile(ct, key))
+        .unwrap();
+    print!("{}", hexdump(&forged));
 
-    let dec = ecb_decrypt(AES128, &to, &key).unwrap();
+    let dec = ecb_decrypt(AES128, &forged, &key).unwrap();
     println!("{}", from_utf8(&dec).unwrap());
-    match kvs(&dec) {
-        Done(_, o) => println!("{:?}", o),
-        _ => println!("failed")
-    }
-}
-
-// Return the first block that matches the next one, and its position in units
-// of block_size.
-fn adj_blocks_match(known_pt: &[u8], block_size: usize, oracle: &Oracle) -> Option<(usize, Vec<u8>)> {
-    let bytes = oracle(known_pt);
-    let block_count = bytes.len() / block_size;
-    for i in 0..block_count-1 {
-        let first  = &bytes[block_size * i      ..block_size * (i + 1)];
-        let second = &bytes[block_size * (i + 1)..block_size * (i + 2)];
-        if  first == second {
-            return Some((i, first.to_vec()));
-        }
-    }
-    None
-}
-
-fn guess_prefix_length(oracle: &Oracle, block_size: usize) -> usize {
-    // Passing this into the oracle is guaranteed to result in two adjacent
-    // matching blocks, so we can safely unwrap.
-    let mut prefix_test = vec![b'A'; block_size*3];
-    let res = adj_blocks_match(&prefix_test, block_size, oracle).unwrap();
-    let ix = res.0;
-    let to_match = res.1;
-
-    // (To be safe, we should repeat prefix_test with vec![b'B'; block_size*3]
-    // which will let us confirm where our injected text is going in case
-    // the prefix or the plaintext results in false positives.
-    // For instance, there's a chance that the prefix contains matching
-    // adjacent blocks, or that the last fractional block of prefix text is
-    // all b'A'.)
-
-    // Let's remove elements until we stop having two adjacent matching blocks
-    // We gotta be careful here because adj_blocks_match might find a match
-    // due to the actual unknown plaintext, but we only want to find matches
-    // due to the attacker-supplied plaintext.
-    while Some(&to_match) == adj_blocks_match(&prefix_test, block_size, oracle).map(|x| x.1).as_ref() {
-        prefix_test.pop();
-    }
-    let n = prefix_test.len() + 1;
-    let prefix_pad = n % block_size;
-
-    ix * block_size - prefix_pad
+    let profile = Cookie::parse(&dec, b'&').unwrap();
+    assert_eq!(profile.get(b"role"), Some(&b"admin"[..]));
 }
 
 fn _14() {
@@ -330,21 +195,13 @@ fn _14() {
     // encrypt the whole thing under ECB under a random key.
     // Given that the same key, prefix, and message are used each time,
     // we can decrypt the message.
-    let key: [u8; 16] = rand::random();
-    let prefix_length: u8 = rand::random();
-    let mut prefix = Vec::new();
-    for _ in 0..prefix_length {
-        prefix.push(rand::random());
-    }
     let unknown = base64_decode_filter(
         include_bytes!("../data/12.txt"));
-    let oracle = &(move |bytes: &[u8]| {
-        let mut v = Vec::new();
-        v.extend_from_slice(&prefix);
-        v.extend_from_slice(bytes);
-        v.extend_from_slice(&unknown);
-        ecb_encrypt(AES128, &v, &key)
-    });
+    let oracle = &Counted::new(
+        Oracle::new(Mode::ECB)
+            .with_random_prefix(0, 256)
+            .with_suffix(&unknown)
+    );
 
     // Let's figure out how long the prefix is.
     // To do that, we first need to determine the block size
@@ -357,6 +214,7 @@ fn _14() {
     let unpad_res = undo_pkcs7(&res);
 
     assert_eq!(&include_bytes!("../data/rollin.txt")[..], &unpad_res[..]);
+    println!("Broke it in {} queries", oracle.queries());
 }
 
 fn _15() {
@@ -366,95 +224,41 @@ fn _15() {
     assert!(undo_pkcs7_checked(b"ICE ICE BABY\x01\x02\x03\x04").is_none());
 }
 
-/*
-    0000100000100001 <- 0 is \x00 and 1 is \x01
-xor AAAA:admin<true: <- : and < are legal
---------------------
-    AAAA;admin=true;
-
-We can pass any two blocks we want as long as their bitwise XOR results in a
-string containing ";admin=true;". In fact, we can even just pass
-    0000000000000000
-    0000000000000000
-and then just write
-    AAAA;admin=true;
-to the ciphertext of the first block, which will flip the bits in the
-decrypted text of the second block so that it reads "AAAA;admin=true;".
-
-oracle pt:
-    comment1=cooking    prefix
-    %20MCs;userdata=    prefix
-    [   whatever   ] <- insert
-    AAAA:admin<true: <- insert
-    ;comment2=%20lik    postfix
-    e%20a%20pound%20    postfix
-    of%20bacon______    postfix
-->
-oracle ct:
-    [ct block 1]
-    [ct block 2]
-    [ct block 3] <- flip the lowest bit in these bytes ____X_____X____X
-    [ct block 4] <- which will result in the corresponding bits in this block
-    [ct block 5]        being flipped after decryption (and scramble block 3)
-    [ct block 6]
-    [ct block 7]
-->
-decrypted:
-    comment1=cooking
-    %20MCs;userdata=
-    ????????????????
-    AAAA;admin=true; <- omg!!!
-    ;comment2=%20lik
-    e%20a%20pound%20
-    of%20bacon______
-*/
 fn _16() {
     let key: [u8; 16] = rand::random();
     let iv: [u8; 16] = rand::random();
-    let oracle = |bytes: &[u8]| {
-        let mut v = Vec::new();
-        v.extend_from_slice(b"comment1=cooking");
-        v.extend_from_slice(b"%20MCs;userdata=");
-        v.extend_from_slice(bytes);
-        v.extend_from_slice(b";comment2=%20lik");
-        v.extend_from_slice(b"e%20a%20pound%20");
-        v.extend_from_slice(b"of%20bacon");
-        cbc_encrypt(AES128, &v, &key, &iv)
-    };
+    let oracle = |bytes: &[u8]| cbc_encrypt(AES128, &comments_for(bytes), &key, &iv);
 
     let authenticate = |ciphertext: &[u8]| {
-        let res = cbc_decrypt(AES128, ciphertext, &key, &iv).unwrap();
-        let needle = b";admin=true;";
-        res.windows(needle.len()).position(|window| window == needle)
+        match cbc_decrypt(AES128, ciphertext, &key, &iv) {
+            Ok(res) => {
+                let needle = b";admin=true;";
+                res.windows(needle.len()).any(|window| window == needle)
+            },
+            Err(_) => false
+        }
     };
 
-    let block_size = 16;
-
-    // We can't pass the string ";admin=true;" into the oracle, but we
-    // can pass ":admin<true:", which is off by only three bits.
-    // y represents the bit locations where it's off.
-    // (We can actually pass in pretty much whatever we want as long as we
-    // flip the right bits in the ciphertext.)
-    let almost = b"AAAA:admin<true:";
-    let goal   = b"AAAA;admin=true;";
-    let y = fixed_xor(almost, goal).unwrap();
-
-    // Pass two block-aligned chunks to the oracle. Because we know that the
-    // prefix is 32 bytes long, we don't need to worry about padding.
-    let mut evil = Vec::new();
-    evil.extend_from_slice(&y);
-    evil.extend_from_slice(almost);
-    let mut enc = oracle(&evil);
-
-    // Now modify the ciphertext so that the first block we passed in has its
-    // bits flipped. When the ciphertext gets decrypted, those bits in the
-    // *next* block get flipped, which turns "AAAA:admin<true:" into
-    // "AAAA;admin=true;"
-    for i in 0..block_size {
-        enc[2 * block_size + i] ^= y[i];
-    }
+    // We can't pass the string ";admin=true;" into the oracle, but we can
+    // pass filler of the same length and flip it into ";admin=true;" through
+    // the ciphertext block before it. bitflip (in bitflip.rs) finds where the
+    // filler lands and does the flipping.
+    let forged = bitflip(&oracle, Mode::CBC, b";admin=true;", authenticate).unwrap();
+    assert!(authenticate(&forged));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // If is_some returns true then the substring ";admin=true;" was found.
-    let is_admin = authenticate(&enc).is_some();
-    assert!(is_admin);
+    #[test]
+    fn test_cut_and_paste_over_http() {
+        let key = rand::random();
+        let server = Server::spawn(Router::new().route("/profile", profile_handler(key))).unwrap();
+        let oracle = &Remote::new(server.addr(), "/profile");
+
+        let forged = cut_and_paste(oracle, b"user", b"admin", |ct: &[u8]| is_admin_profile(ct, key))
+            .unwrap();
+        assert!(is_admin_profile(&forged, key));
+    }
 }
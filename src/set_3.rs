@@ -1,14 +1,17 @@
 use aes::*;
 use blockmode::*;
+use clock::*;
 use codec::*;
+use http::*;
 use mt::*;
+use oracle::*;
+use transcript::*;
 use xor::*;
 
 use rand;
 use rand::Rng;
 use std::str::from_utf8;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use std::thread::sleep;
+use std::sync::Arc;
 
 pub fn set_3() {
     println!("Set 3");
@@ -52,8 +55,29 @@ impl CBCServer {
     }
 }
 
+impl PaddingOracle for CBCServer {
+    fn padding_ok(&self, ct: &[u8]) -> bool {
+        self.verify_aes_128_cbc(ct)
+    }
+}
+
+// GET /17/ciphertext gives the hex of an IV and a ciphertext, and
+// GET /17/padding?input=<hex> says whether its padding is good.
+pub fn routes(router: Router) -> Router {
+    let server = Arc::new(CBCServer::new());
+    let ciphertexts = server.clone();
+    router
+        .route("/17/ciphertext", Box::new(move |_: &Request| {
+            let mut body = ciphertexts.get_iv().to_vec();
+            body.extend(ciphertexts.get_random_ciphertext());
+            Response::ok(&base16_encode(&body))
+        }))
+        .route("/17/padding", padding_handler(move |ct: &[u8]| server.verify_aes_128_cbc(ct)))
+}
+
 // Just decrypt one block, given its predecessor
-fn cbc_padding_attack_block(block: &[u8], server: &CBCServer, prev: &[u8]) -> Vec<u8> {
+fn cbc_padding_attack_block<O>(block: &[u8], server: &O, prev: &[u8])
+    -> Result<Vec<u8>, String> where O: PaddingOracle {
     let mut known_bytes = vec![0; 16];
 
     let mut test_ct = Vec::new();
@@ -69,13 +93,13 @@ fn cbc_padding_attack_block(block: &[u8], server: &CBCServer, prev: &[u8]) -> Ve
         let orig = test_ct[col];
         for guess in 0..=255 {
             test_ct[col] = guess;
-            let maybe_valid = server.verify_aes_128_cbc(&test_ct);
+            let maybe_valid = server.padding_ok(&test_ct);
             // It's possible that the padding isn't what we think it is, so
             // mutate the preceding byte in case it's interfering.
             if col > 0 {
                 test_ct[col - 1] ^= 0x01;
             }
-            let definitely_valid = maybe_valid && server.verify_aes_128_cbc(&test_ct);
+            let definitely_valid = maybe_valid && server.padding_ok(&test_ct);
             if col > 0 {
                 test_ct[col - 1] ^= 0x01;
             }
@@ -87,13 +111,16 @@ fn cbc_padding_attack_block(block: &[u8], server: &CBCServer, prev: &[u8]) -> Ve
                 break;
             }
         }
-        assert!(valid_found, " valid_found failed when col={}", col);
+        if !valid_found {
+            return Err(format!("No guess gave valid padding when col={}", col));
+        }
     }
     println!("Learned block: {}", from_utf8(&known_bytes).unwrap());
-    known_bytes
+    Ok(known_bytes)
 }
 
-fn cbc_padding_attack(bytes: &[u8], server: &CBCServer, iv: [u8; 16]) -> Vec<u8> {
+fn cbc_padding_attack<O>(bytes: &[u8], server: &O, iv: [u8; 16]) -> Result<Vec<u8>, String>
+    where O: PaddingOracle {
     let mut out = Vec::new();
 
     let mut prev = Vec::new();
@@ -105,19 +132,22 @@ fn cbc_padding_attack(bytes: &[u8], server: &CBCServer, iv: [u8; 16]) -> Vec<u8>
             (block_index+1)*16
         ]);
         out.extend_from_slice(
-            &cbc_padding_attack_block(&block, server, &prev)
+            &cbc_padding_attack_block(&block, server, &prev)?
         );
         prev = block;
     }
 
-    out
+    Ok(out)
 }
 
 fn _17() {
     let server = CBCServer::new();
     let enc = server.get_random_ciphertext();
-    assert!(server.verify_aes_128_cbc(&enc));
-    cbc_padding_attack(&enc, &server, server.get_iv());
+    let iv = server.get_iv();
+    let oracle = Recorder::new(server);
+    assert!(oracle.padding_ok(&enc));
+    cbc_padding_attack(&enc, &oracle, iv).unwrap();
+    println!("Used {} padding oracle queries", oracle.queries());
 }
 
 fn _18() {
@@ -166,47 +196,36 @@ fn _21() {
     }
 }
 
-fn crack_seed(first_output: u32, start: u32, finish: u32) -> Option<u32> {
-    for i in start..=finish {
-        let mut mt = MersenneTwister::new(i);
-        let first = mt.next();
-        if first == first_output {
-            return Some(i)
-        }
-    }
-    return None
-}
-
-fn get_unix_time() -> u32 {
-    SystemTime::now().duration_since(UNIX_EPOCH)
-        .expect("Time went backwards").as_secs() as u32
-}
-
-fn wait_a_sec() {
+fn wait_a_sec<C: Clock>(clock: &C) {
     let sample = rand::seq::sample_iter(&mut rand::thread_rng(), 40..1000, 1)
         .unwrap()[0];
-    sleep(Duration::from_secs(sample));
+    clock.sleep(sample);
 }
 
 fn _22() {
-    let mut rng = rand::thread_rng();
-    let start = get_unix_time();
-    wait_a_sec();
-    let since_the_epoch = get_unix_time();
+    timestamp_seed(&SystemClock);
+}
+
+// Seed the MT with the current time, then work out the seed from a few of its
+// outputs. We don't get to see the first output, just some later ones.
+fn timestamp_seed<C: Clock>(clock: &C) {
+    let start = clock.now();
+    wait_a_sec(clock);
+    let since_the_epoch = clock.now();
     let mut mt = MersenneTwister::new(since_the_epoch);
-    wait_a_sec();
-    let output = mt.next();
-    let finish = get_unix_time();
-    let the_seed = crack_seed(output, start, finish);
-
-    match the_seed {
-        Some(seed) => assert_eq!(seed, since_the_epoch),
-        None       => unreachable!()
-    }
+    wait_a_sec(clock);
+    let outputs = (0..5)
+        .map(|pos| (pos, mt.next()))
+        .skip(2)
+        .collect::<Vec<_>>();
+    let finish = clock.now();
+    let candidates = crack_seed(&outputs, start, finish);
+
+    assert_eq!(candidates, vec![since_the_epoch]);
 }
 
 fn _23() {
-    let time = get_unix_time();
+    let time = SystemClock.now();
     let mut mt = MersenneTwister::new(time);
     let mut outputs = [0; 624];
     for o in outputs {
@@ -214,3 +233,41 @@ fn _23() {
         o = MersenneTwister::untemper(random);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pkcs::*;
+
+    #[test]
+    fn test_timestamp_seed() {
+        timestamp_seed(&FakeClock::new(1_500_000_000));
+    }
+
+    #[test]
+    fn test_padding_attack_budget() {
+        let server = CBCServer::new();
+        let enc = server.get_random_ciphertext();
+        let iv = server.get_iv();
+        let oracle = Recorder::new(server).with_budget(100);
+        assert!(cbc_padding_attack(&enc, &oracle, iv).is_err());
+        assert_eq!(oracle.error(), Some("query budget of 100 exhausted".to_string()));
+        assert_eq!(oracle.queries(), 100);
+    }
+
+    #[test]
+    fn test_padding_attack_over_http() {
+        let server = Server::spawn(routes(Router::new())).unwrap();
+        let response = Remote::new(server.addr(), "/17/ciphertext").get(&[]);
+        let body = base16_decode(&response.body).unwrap();
+        let mut iv = [0; 16];
+        iv.copy_from_slice(&body[..16]);
+
+        let oracle = Remote::new(server.addr(), "/17/padding");
+        let pt = cbc_padding_attack(&body[16..], &oracle, iv).unwrap();
+        let pt = undo_pkcs7_checked(&pt).unwrap();
+        assert!(include_bytes!("../data/17.txt")
+            .split(|byte| byte == &b'\n')
+            .any(|line| base64_decode(line).unwrap() == pt));
+    }
+}
@@ -0,0 +1,202 @@
+use aes::*;
+use blockmode::*;
+use codec::*;
+use cookie::*;
+use digest::*;
+use extension::*;
+use hmac::*;
+use http::*;
+use md4::*;
+use oracle::*;
+use sha1::*;
+
+use rand;
+use std::str::from_utf8;
+use std::time::Duration;
+
+pub fn set_4() {
+    println!("Set 4");
+    _27();
+    _28();
+    _29();
+    _30();
+    _31();
+    _32();
+}
+
+// Uses the key as its IV, and complains about non-ASCII plaintext by quoting
+// the plaintext back at us.
+struct KeyIvServer {
+    aes_key: [u8; 16]
+}
+
+impl KeyIvServer {
+    pub fn new() -> Self {
+        KeyIvServer { aes_key: rand::random() }
+    }
+
+    pub fn encrypt_comments(&self, userdata: &[u8]) -> Vec<u8> {
+        cbc_encrypt_key_iv(AES128, &comments_for(userdata), &self.aes_key)
+    }
+
+    pub fn is_admin(&self, bytes: &[u8]) -> bool {
+        self.decrypt(bytes)
+            .and_then(|pt| Cookie::parse(&pt, b';'))
+            .map(|cookie| cookie.get(b"admin") == Some(b"true"))
+            .unwrap_or(false)
+    }
+}
+
+impl DecryptionOracle for KeyIvServer {
+    fn decrypt(&self, ct: &[u8]) -> Result<Vec<u8>, String> {
+        let pt = cbc_decrypt_key_iv(AES128, ct, &self.aes_key)?;
+        if pt.iter().any(|&b| b >= 0x80) {
+            return Err(format!("Invalid ASCII in plaintext: {}",
+                from_utf8(&base16_encode(&pt)).unwrap()));
+        }
+        Ok(pt)
+    }
+}
+
+// Decrypting C1 || 0 || C1 gives P1 || junk || D(C1). P1 = D(C1) ^ IV, so
+// P1 ^ P3 = IV, which is the key. The junk is almost certainly high-ASCII,
+// so the server hands the plaintext right back. Putting the original last
+// two blocks after that keeps the padding valid, however short the
+// ciphertext is.
+fn recover_key_as_iv<O>(oracle: &O, ct: &[u8]) -> Result<[u8; 16], String>
+    where O: DecryptionOracle {
+    if ct.len() < 32 || ct.len() % 16 != 0 {
+        return Err("Need at least two whole blocks of ciphertext".to_string());
+    }
+    let mut evil = ct[..16].to_vec();
+    evil.extend(&[0; 16]);
+    evil.extend(&ct[..16]);
+    evil.extend(&ct[ct.len() - 32..]);
+
+    let message = match oracle.decrypt(&evil) {
+        Ok(_) => return Err(
+            "Server accepted the forged ciphertext as ASCII, so no plaintext leaked".to_string()),
+        Err(message) => message
+    };
+    let hex = message.rsplit(' ').next().unwrap();
+    let pt = base16_decode(hex.as_bytes())?;
+    if pt.len() < 48 {
+        return Err(format!("Unexpected error: {}", message));
+    }
+
+    let mut key = [0; 16];
+    for i in 0..16 {
+        key[i] = pt[i] ^ pt[32 + i];
+    }
+    Ok(key)
+}
+
+fn _27() {
+    let server = KeyIvServer::new();
+    let ct = server.encrypt_comments(b"just a regular user");
+    assert!(!server.is_admin(&ct));
+
+    let key = recover_key_as_iv(&server, &ct).unwrap();
+    assert_eq!(key, server.aes_key);
+
+    // With the key, we can write whatever we like.
+    let mut cookie = Cookie::new();
+    cookie.set(b"admin", b"true");
+    let forged = cbc_encrypt_key_iv(AES128, &cookie.encode(b';'), &key);
+    assert!(server.is_admin(&forged));
+}
+
+fn _28() {
+    let key = b"YELLOW SUBMARINE";
+    let message = b"The Magic Words are Squeamish Ossifrage";
+    let mac = secret_prefix_mac::<Sha1>(key, message);
+    assert_eq!(mac, sha1(&[&key[..], &message[..]].concat()));
+    assert!(secret_prefix_mac::<Sha1>(key, b"The Magic Words are Squeamish Ossifragf") != mac);
+    assert!(secret_prefix_mac::<Sha1>(b"YELLOW SUBMARINF", message) != mac);
+}
+
+fn random_secret() -> Vec<u8> {
+    let length = 8 + rand::random::<usize>() % 24;
+    (0..length).map(|_| rand::random()).collect()
+}
+
+// forge_with_key (and its tests in extension.rs) does the real work; this
+// just sets it up as the challenges describe.
+fn forge_admin<D>() -> Forgery where D: Digest {
+    let key = random_secret();
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let forgery = forge_with_key::<D>(&key, message, 64, b";admin=true").unwrap();
+    assert_eq!(forgery.secret_length, key.len());
+    forgery
+}
+
+fn _29() {
+    println!("{}", String::from_utf8_lossy(&forge_admin::<Sha1>().message));
+}
+
+fn _30() {
+    println!("{}", String::from_utf8_lossy(&forge_admin::<Md4>().message));
+}
+
+// The server waits after every byte of the MAC it gets right, so we can read
+// the right MAC off its response times. The challenges use 50ms and then 5ms
+// over a whole 20-byte MAC, which takes hours. These delays are shorter and
+// the server only checks the first `mac_length` bytes, so it takes seconds;
+// the attack is the same however long the MAC is.
+fn break_leaky_hmac(delay: Duration, rounds: usize, mac_length: usize) {
+    let key = random_secret();
+    let verifier = LeakyVerifier::new(&key, delay).with_mac_length(mac_length);
+    let file = b"foo";
+    let mac = timing_attack(mac_length, rounds, |mac: &[u8]| verifier.verify::<Sha1>(file, mac))
+        .unwrap();
+    assert_eq!(mac, hmac::<Sha1>(&key, file)[..mac_length].to_vec());
+}
+
+// GET /test?file=<name>&signature=<hex>, as in challenge 31.
+pub fn routes(router: Router) -> Router {
+    let verifier = LeakyVerifier::new(&random_secret(), Duration::from_millis(5));
+    router.route("/test", hmac_handler::<Sha1>(verifier))
+}
+
+fn _31() {
+    break_leaky_hmac(Duration::from_millis(2), 3, 4);
+}
+
+fn _32() {
+    break_leaky_hmac(Duration::from_millis(1), 5, 4);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recover_key_as_iv() {
+        _27();
+        let server = KeyIvServer::new();
+        let short = server.encrypt_comments(b"");
+        assert!(recover_key_as_iv(&server, &short[..16]).is_err());
+        assert!(recover_key_as_iv(&server, &short[..40]).is_err());
+
+        // Two, three and four blocks of ciphertext.
+        for &length in &[16, 40, 48] {
+            let ct = cbc_encrypt_key_iv(AES128, &vec![b'a'; length], &server.aes_key);
+            assert_eq!(recover_key_as_iv(&server, &ct), Ok(server.aes_key));
+        }
+    }
+
+    #[test]
+    fn test_secret_prefix_mac() {
+        _28();
+    }
+
+    #[test]
+    fn test_forge_sha1_mac() {
+        assert!(forge_admin::<Sha1>().message.ends_with(b";admin=true"));
+    }
+
+    #[test]
+    fn test_forge_md4_mac() {
+        assert!(forge_admin::<Md4>().message.ends_with(b";admin=true"));
+    }
+}
@@ -0,0 +1,118 @@
+use digest::*;
+
+use byteorder::{BigEndian, ByteOrder};
+
+const H0: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+#[derive(Debug, Clone)]
+pub struct Sha1Compress;
+
+pub type Sha1 = MdHash<Sha1Compress>;
+
+impl Compress for Sha1Compress {
+    fn initial_state() -> Vec<u32> {
+        H0.to_vec()
+    }
+
+    fn big_endian() -> bool {
+        true
+    }
+
+    fn compress(state: &mut [u32], block: &[u8]) {
+        let mut w = [0u32; 80];
+        BigEndian::read_u32_into(block, &mut w[..16]);
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let (mut a, mut b, mut c, mut d, mut e) = (state[0], state[1], state[2], state[3], state[4]);
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19  => ((b & c) | (!b & d),          0x5A827999),
+                20..=39 => (b ^ c ^ d,                   0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _       => (b ^ c ^ d,                   0xCA62C1D6)
+            };
+            let temp = a.rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (h, v) in state.iter_mut().zip(&[a, b, c, d, e]) {
+            *h = h.wrapping_add(*v);
+        }
+    }
+}
+
+pub fn sha1(data: &[u8]) -> Vec<u8> {
+    Sha1::digest(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::*;
+
+    fn hex(digest: Vec<u8>) -> Vec<u8> {
+        base16_encode(&digest)
+    }
+
+    #[test]
+    fn test_sha1_vectors() {
+        assert_eq!(hex(sha1(b"abc")), b"a9993e364706816aba3e25717850c26c9cd0d89d".to_vec());
+        assert_eq!(hex(sha1(b"")), b"da39a3ee5e6b4b0d3255bfef95601890afd80709".to_vec());
+        assert_eq!(
+            hex(sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+            b"84983e441c3bd26ebaae4aa1f95129e5e54670f1".to_vec()
+        );
+        assert_eq!(
+            hex(sha1(b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+                       hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu")),
+            b"a49b2446a02c645bf419f995b67091253a04a259".to_vec()
+        );
+
+        let mut hasher = Sha1::new();
+        for _ in 0..1000 {
+            hasher.update(&[b'a'; 1000]);
+        }
+        assert_eq!(hex(hasher.finalize()), b"34aa973cd4c4daa4f61eeb2bdbad27316534016f".to_vec());
+    }
+
+    #[test]
+    fn test_sha1_streaming() {
+        let data: Vec<u8> = (0..1000).map(|i| (i * 7) as u8).collect();
+        for &split in &[0, 1, 55, 56, 63, 64, 65, 500, 1000] {
+            let mut hasher = Sha1::new();
+            hasher.update(&data[..split]);
+            hasher.update(&data[split..]);
+            assert_eq!(hasher.finalize(), sha1(&data));
+        }
+    }
+
+    #[test]
+    fn test_sha1_from_state() {
+        // Carrying on from a digest is the same as hashing the padded message
+        // and then some.
+        let message = b"comment1=cooking%20MCs;userdata=foo";
+        let mut glued = message.to_vec();
+        glued.extend(Sha1::padding(message.len() as u64));
+        let glued_len = glued.len() as u64;
+        glued.extend(b";admin=true");
+
+        let mut hasher = Sha1::from_digest(&sha1(message), glued_len);
+        hasher.update(b";admin=true");
+        assert_eq!(hasher.finalize(), sha1(&glued));
+
+        let mut hasher = Sha1::new();
+        hasher.update(&[0; 64]);
+        let resumed = Sha1::from_state(hasher.state(), 64);
+        assert_eq!(resumed.finalize(), hasher.finalize());
+    }
+}
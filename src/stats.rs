@@ -1,4 +1,15 @@
+use codec::*;
+
 use itertools::zip;
+use std::collections::HashMap;
+use std::f32;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::str::from_utf8;
+
+pub static ENGLISH_CORPUS: &[u8] = include_bytes!("../data/english.txt");
 
 pub fn alph(c: u8) -> Option<u8> {
     if c >= 65 && c <= 90 {
@@ -32,34 +43,592 @@ pub fn diff(v1: &[f32], v2: &[f32]) -> Option<f32> {
     }
 }
 
+// Percentages for each letter of the alphabet, one `letter<TAB>percentage`
+// line each, like data/letterfreq.txt.
+pub fn parse_letter_freqs(text: &str) -> Result<Vec<f32>, String> {
+    let mut freqs = vec![0.0; 26];
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let mut fields = line.split('\t');
+        let letter = fields.next()
+            .and_then(|f| f.bytes().next())
+            .and_then(alph);
+        let freq = fields.next().and_then(|f| f.trim().parse::<f32>().ok());
+        match (letter, freq) {
+            (Some(ix), Some(freq)) => freqs[ix as usize] = freq,
+            _ => return Err(format!("Malformed letter frequency line {:?}", line))
+        }
+    }
+    Ok(freqs)
+}
+
+pub fn english_letter_freqs() -> Vec<f32> {
+    parse_letter_freqs(include_str!("../data/letterfreq.txt"))
+        .expect("data/letterfreq.txt is malformed")
+}
+
+// `score` runs for every candidate key, so the file is parsed once, the first
+// time it's needed, rather than on every call.
+thread_local!(static ENGLISH_LETTERS: Vec<f32> = english_letter_freqs());
+
+pub fn letter_score(s: &[u8], expected: &[f32]) -> f32 {
+    diff(&histo(s), expected).unwrap()
+}
+
 pub fn score(s: &[u8]) -> f32 {
-    let english_freq = [
-        8.167,
-        1.492,
-        2.782,
-        4.253,
-        12.702,
-        2.228,
-        2.015,
-        6.094,
-        6.966,
-        0.153,
-        0.772,
-        4.025,
-        2.406,
-        6.749,
-        7.507,
-        1.929,
-        0.095,
-        5.987,
-        6.327,
-        9.056,
-        2.758,
-        0.978,
-        2.360,
-        0.150,
-        1.974,
-        0.074
-    ];
-    diff(&histo(s), &english_freq).unwrap()
+    ENGLISH_LETTERS.with(|freqs| letter_score(s, freqs))
+}
+
+// Anything that can rate how much a candidate plaintext looks like what we
+// expect it to. Lower is better, same as `score`, so plain functions like
+// `score` work as scorers too.
+pub trait Scorer {
+    fn score(&self, s: &[u8]) -> f32;
+}
+
+impl<F> Scorer for F where F: Fn(&[u8]) -> f32 {
+    fn score(&self, s: &[u8]) -> f32 {
+        self(s)
+    }
+}
+
+// Relative frequency of every byte value in `corpus`. Bytes that never show
+// up get a small pseudo-count, so they're unlikely rather than impossible.
+pub fn byte_freqs(corpus: &[u8]) -> Vec<f32> {
+    normalize_byte_counts(&byte_counts(corpus))
+}
+
+fn normalize_byte_counts(counts: &[f32]) -> Vec<f32> {
+    let pseudo_count = 0.01;
+    let total = counts.iter().sum::<f32>() + pseudo_count * 256.0;
+    counts.iter().map(|count| (count + pseudo_count) / total).collect()
+}
+
+fn byte_counts(s: &[u8]) -> Vec<f32> {
+    let mut counts = vec![0.0; 256];
+    for &byte in s {
+        counts[byte as usize] += 1.0;
+    }
+    counts
+}
+
+// Like `score`, but over all 256 byte values, so spaces, punctuation and
+// binary junk count for something.
+pub struct Unigram {
+    expected: Vec<f32>
+}
+
+impl Unigram {
+    pub fn new(expected: Vec<f32>) -> Self {
+        assert_eq!(expected.len(), 256);
+        Unigram { expected: expected }
+    }
+
+    pub fn english() -> Self {
+        Unigram::new(byte_freqs(ENGLISH_CORPUS))
+    }
+}
+
+impl Scorer for Unigram {
+    fn score(&self, s: &[u8]) -> f32 {
+        let len = s.len().max(1) as f32;
+        let observed: Vec<f32> = byte_counts(s).iter().map(|c| c / len).collect();
+        diff(&observed, &self.expected).unwrap()
+    }
+}
+
+// Pearson's chi-squared statistic against expected byte frequencies. Much
+// harsher than `Unigram` on bytes that should almost never appear. Empty
+// input scores worst of all rather than 0/0, since a NaN would upset any
+// ranking it ended up in.
+pub struct ChiSquared {
+    expected: Vec<f32>
+}
+
+impl ChiSquared {
+    pub fn new(expected: Vec<f32>) -> Self {
+        assert_eq!(expected.len(), 256);
+        ChiSquared { expected: expected }
+    }
+
+    pub fn english() -> Self {
+        ChiSquared::new(byte_freqs(ENGLISH_CORPUS))
+    }
+}
+
+impl Scorer for ChiSquared {
+    fn score(&self, s: &[u8]) -> f32 {
+        if s.is_empty() {
+            return f32::INFINITY;
+        }
+        let len = s.len() as f32;
+        zip(byte_counts(s), &self.expected)
+            .map(|(observed, p)| {
+                let expected = len * p;
+                (observed - expected).powi(2) / expected
+            })
+            .sum()
+    }
+}
+
+// Log-probabilities of every n-byte sequence seen in a corpus. Scores are
+// the negated average log-likelihood per n-gram, so they're comparable
+// between inputs of different lengths.
+pub struct Ngrams {
+    n: usize,
+    log_probs: HashMap<Vec<u8>, f32>,
+    floor: f32
+}
+
+impl Ngrams {
+    pub fn from_corpus(corpus: &[u8], n: usize) -> Self {
+        let mut counts = HashMap::new();
+        for gram in corpus.windows(n) {
+            *counts.entry(gram.to_vec()).or_insert(0) += 1;
+        }
+        Ngrams::from_counts(n, &counts)
+    }
+
+    // Only the n-grams of length `n` in `counts` are used.
+    pub fn from_counts(n: usize, counts: &HashMap<Vec<u8>, u64>) -> Self {
+        let grams: Vec<(&Vec<u8>, f32)> = counts.iter()
+            .filter(|&(gram, _)| gram.len() == n)
+            .map(|(gram, &count)| (gram, count as f32))
+            .collect();
+        let total = grams.iter().map(|&(_, count)| count).sum::<f32>().max(1.0);
+        Ngrams {
+            n: n,
+            log_probs: grams.into_iter()
+                .map(|(gram, count)| (gram.clone(), (count / total).ln()))
+                .collect(),
+            floor: (0.01 / total).ln()
+        }
+    }
+
+    pub fn english_bigrams() -> Self {
+        Ngrams::from_corpus(ENGLISH_CORPUS, 2)
+    }
+
+    pub fn english_trigrams() -> Self {
+        Ngrams::from_corpus(ENGLISH_CORPUS, 3)
+    }
+
+    pub fn log_likelihood(&self, s: &[u8]) -> f32 {
+        s.windows(self.n)
+            .map(|gram| *self.log_probs.get(gram).unwrap_or(&self.floor))
+            .sum()
+    }
+}
+
+impl Scorer for Ngrams {
+    fn score(&self, s: &[u8]) -> f32 {
+        if s.len() < self.n {
+            return -self.floor;
+        }
+        -self.log_likelihood(s) / (s.len() - self.n + 1) as f32
+    }
+}
+
+// The fraction of `s` that's printable ASCII or ordinary whitespace.
+pub fn printable_ratio(s: &[u8]) -> f32 {
+    if s.is_empty() {
+        return 1.0;
+    }
+    let printable = s.iter()
+        .filter(|&&b| (b >= 0x20 && b < 0x7f) || b == b'\t' || b == b'\r' || b == b'\n')
+        .count();
+    printable as f32 / s.len() as f32
+}
+
+// Wraps another scorer and adds `weight` times the fraction of unprintable
+// bytes, for scorers that would otherwise be fooled by binary garbage.
+pub struct PrintablePenalty<S> {
+    inner: S,
+    weight: f32
+}
+
+impl<S: Scorer> PrintablePenalty<S> {
+    pub fn new(inner: S, weight: f32) -> Self {
+        PrintablePenalty { inner: inner, weight: weight }
+    }
+}
+
+impl<S: Scorer> Scorer for PrintablePenalty<S> {
+    fn score(&self, s: &[u8]) -> f32 {
+        self.inner.score(s) + self.weight * (1.0 - printable_ratio(s))
+    }
+}
+
+// Byte, bigram and trigram counts from a training corpus, for scoring
+// plaintexts that aren't English prose: other languages, JSON, source code,
+// logs. Models are saved as text, one `<hex n-gram><TAB><count>` line per
+// n-gram, so they can be inspected and diffed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model {
+    counts: HashMap<Vec<u8>, u64>
+}
+
+impl Model {
+    pub fn train(corpus: &[u8]) -> Self {
+        let mut counts = HashMap::new();
+        for n in 1..=3 {
+            for gram in corpus.windows(n) {
+                *counts.entry(gram.to_vec()).or_insert(0) += 1;
+            }
+        }
+        Model { counts: counts }
+    }
+
+    pub fn train_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut corpus = Vec::new();
+        File::open(path)?.read_to_end(&mut corpus)?;
+        Ok(Model::train(&corpus))
+    }
+
+    pub fn count(&self, gram: &[u8]) -> u64 {
+        *self.counts.get(gram).unwrap_or(&0)
+    }
+
+    pub fn byte_freqs(&self) -> Vec<f32> {
+        let counts: Vec<f32> = (0..=255u8).map(|b| self.count(&[b]) as f32).collect();
+        normalize_byte_counts(&counts)
+    }
+
+    // Case-insensitive percentages, in the same shape as `parse_letter_freqs`.
+    pub fn letter_freqs(&self) -> Vec<f32> {
+        let mut freqs = vec![0.0; 26];
+        for b in 0..=255u8 {
+            if let Some(ix) = alph(b) {
+                freqs[ix as usize] += self.count(&[b]) as f32;
+            }
+        }
+        let total = freqs.iter().sum::<f32>().max(1.0);
+        freqs.iter().map(|count| 100.0 * count / total).collect()
+    }
+
+    pub fn unigram(&self) -> Unigram {
+        Unigram::new(self.byte_freqs())
+    }
+
+    pub fn chi_squared(&self) -> ChiSquared {
+        ChiSquared::new(self.byte_freqs())
+    }
+
+    pub fn ngrams(&self, n: usize) -> Ngrams {
+        Ngrams::from_counts(n, &self.counts)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut grams: Vec<(&Vec<u8>, &u64)> = self.counts.iter().collect();
+        grams.sort_by(|a, b| (a.0.len(), a.0).cmp(&(b.0.len(), b.0)));
+        let mut out = Vec::new();
+        for (gram, count) in grams {
+            out.extend(base16_encode(gram));
+            out.extend(format!("\t{}\n", count).bytes());
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut counts = HashMap::new();
+        for (line_ix, line) in bytes.split(|&b| b == b'\n').enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let bad_line = || format!("Malformed model on line {}", line_ix + 1);
+            let tab = line.iter().position(|&b| b == b'\t').ok_or_else(&bad_line)?;
+            let gram = base16_decode(&line[..tab])?;
+            let count = from_utf8(&line[tab + 1..]).ok()
+                .and_then(|c| c.trim().parse::<u64>().ok())
+                .ok_or_else(&bad_line)?;
+            if gram.is_empty() {
+                return Err(bad_line());
+            }
+            counts.insert(gram, count);
+        }
+        Ok(Model { counts: counts })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        File::create(path)?.write_all(&self.to_bytes())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        Model::from_bytes(&bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+// Letter percentages for other languages, from Wikipedia's letter frequency
+// article. English comes from data/letterfreq.txt.
+static GERMAN_LETTERS: [f32; 26] = [
+    6.516, 1.886, 2.732, 5.076, 16.396, 1.656, 3.009, 4.577, 6.550, 0.268,
+    1.417, 3.437, 2.534, 9.776, 2.594, 0.670, 0.018, 7.003, 7.270, 6.154,
+    4.166, 0.846, 1.921, 0.034, 0.039, 1.134
+];
+static FRENCH_LETTERS: [f32; 26] = [
+    7.636, 0.901, 3.260, 3.669, 14.715, 1.066, 0.866, 0.737, 7.529, 0.613,
+    0.074, 5.456, 2.968, 7.095, 5.796, 2.521, 1.362, 6.693, 7.948, 7.244,
+    6.311, 1.838, 0.049, 0.427, 0.128, 0.326
+];
+static SPANISH_LETTERS: [f32; 26] = [
+    11.525, 2.215, 4.019, 5.010, 12.181, 0.692, 1.768, 0.703, 6.247, 0.493,
+    0.011, 4.967, 3.157, 6.712, 8.683, 2.510, 0.877, 6.871, 7.977, 4.632,
+    2.927, 1.138, 0.017, 0.215, 1.008, 0.467
+];
+
+// Second bytes of the UTF-8 encodings of each language's accented letters,
+// all of which start with 0xc3.
+static GERMAN_ACCENTS: &[u8] = b"\xa4\xb6\xbc\x9f\x84\x96\x9c";
+static FRENCH_ACCENTS: &[u8] = b"\xa9\xa8\xaa\xa0\xa7\xb9\xa2\xae\xb4\xbb\xab\xaf";
+static SPANISH_ACCENTS: &[u8] = b"\xa1\xa9\xad\xb3\xba\xb1\xbc";
+
+static JSON_SAMPLE: &[u8] = br#"{"id": 1024, "name": "Alice Smith", "email": "alice@example.com",
+ "roles": ["user", "admin"], "active": true, "score": 98.6, "manager": null,
+ "address": {"street": "12 High Street", "city": "Springfield", "zip": "49007"}}
+[{"key": "timeout", "value": 30}, {"key": "retries", "value": 5, "enabled": false},
+ {"key": "endpoint", "value": "https://api.example.com/v1/items?page=2"}]"#;
+
+pub static MAGIC_NUMBERS: &[(&str, &[u8])] = &[
+    ("PNG",   b"\x89PNG\r\n\x1a\n"),
+    ("GIF",   b"GIF8"),
+    ("JPEG",  b"\xff\xd8\xff"),
+    ("PDF",   b"%PDF-"),
+    ("ZIP",   b"PK\x03\x04"),
+    ("gzip",  b"\x1f\x8b\x08"),
+    ("bzip2", b"BZh"),
+    ("ELF",   b"\x7fELF")
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Profile {
+    English,
+    German,
+    French,
+    Spanish,
+    Base64,
+    Json,
+    Binary,
+    Magic(&'static str)
+}
+
+// Bytes of prose in a language, given its letter percentages: mostly
+// lowercase letters and spaces, with a little of everything else.
+fn language_freqs(letters: &[f32], accents: &[u8]) -> Vec<f32> {
+    let mut freqs = vec![0.00001; 256];
+    freqs[b' ' as usize] += 0.16;
+    for (i, freq) in letters.iter().enumerate() {
+        freqs[b'a' as usize + i] += 0.74 * freq / 100.0;
+        freqs[b'A' as usize + i] += 0.03 * freq / 100.0;
+    }
+    for &b in b".,\n" {
+        freqs[b as usize] += 0.01;
+    }
+    for &b in b"'\"-;:!?()0123456789" {
+        freqs[b as usize] += 0.0007;
+    }
+    if !accents.is_empty() {
+        freqs[0xc3] += 0.01;
+        for &b in accents {
+            freqs[b as usize] += 0.01 / accents.len() as f32;
+        }
+    }
+    freqs
+}
+
+fn base64_freqs() -> Vec<f32> {
+    let mut freqs = vec![0.00001; 256];
+    for &b in B64_TABLE {
+        freqs[b as usize] += 0.97 / 64.0;
+    }
+    freqs[b'=' as usize] += 0.01;
+    freqs[b'\n' as usize] += 0.02;
+    freqs
+}
+
+fn log_probs(freqs: &[f32]) -> Vec<f32> {
+    let total: f32 = freqs.iter().sum();
+    freqs.iter().map(|f| (f / total).ln()).collect()
+}
+
+// Ranks candidate plaintexts against a handful of built-in profiles. Each
+// profile is a byte distribution, and the score is the average negative
+// log-likelihood per byte, so lower is better and scores are comparable
+// across profiles. Uniformly random bytes score ln(256) = 5.55 under the
+// `Binary` profile, which is the bar every other profile has to beat.
+// A matching magic number scores 0, since it's as sure as we can get.
+#[derive(Debug, Clone)]
+pub struct Classifier {
+    profiles: Vec<(Profile, Vec<f32>)>,
+    check_magic: bool
+}
+
+impl Classifier {
+    pub fn new() -> Self {
+        let english = english_letter_freqs();
+        Classifier {
+            profiles: vec![
+                (Profile::English, log_probs(&language_freqs(&english, &[]))),
+                (Profile::German,  log_probs(&language_freqs(&GERMAN_LETTERS, GERMAN_ACCENTS))),
+                (Profile::French,  log_probs(&language_freqs(&FRENCH_LETTERS, FRENCH_ACCENTS))),
+                (Profile::Spanish, log_probs(&language_freqs(&SPANISH_LETTERS, SPANISH_ACCENTS))),
+                (Profile::Base64,  log_probs(&base64_freqs())),
+                (Profile::Json,    log_probs(&byte_freqs(JSON_SAMPLE))),
+                (Profile::Binary,  log_probs(&[1.0; 256]))
+            ],
+            check_magic: true
+        }
+    }
+
+    // Magic numbers only make sense at the start of a whole plaintext, so
+    // turn them off when scoring things like the columns of a repeating XOR.
+    pub fn with_magic(mut self, check_magic: bool) -> Self {
+        self.check_magic = check_magic;
+        self
+    }
+
+    // Every profile and its score, best first.
+    pub fn rank(&self, s: &[u8]) -> Vec<(Profile, f32)> {
+        let len = s.len().max(1) as f32;
+        let mut ranked: Vec<(Profile, f32)> = self.profiles.iter()
+            .map(|&(profile, ref log_probs)| {
+                let total: f32 = s.iter().map(|&b| log_probs[b as usize]).sum();
+                (profile, -total / len)
+            })
+            .collect();
+        if self.check_magic {
+            for &(name, magic) in MAGIC_NUMBERS {
+                if s.starts_with(magic) {
+                    ranked.push((Profile::Magic(name), 0.0));
+                }
+            }
+        }
+        ranked.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        ranked
+    }
+
+    pub fn classify(&self, s: &[u8]) -> (Profile, f32) {
+        self.rank(s)[0]
+    }
+}
+
+impl Scorer for Classifier {
+    fn score(&self, s: &[u8]) -> f32 {
+        self.classify(s).1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_freqs() {
+        let freqs = byte_freqs(ENGLISH_CORPUS);
+        let total: f32 = freqs.iter().sum();
+        assert!((total - 1.0).abs() < 1e-4);
+        assert!(freqs[b' ' as usize] > freqs[b'e' as usize]);
+        assert!(freqs[0] > 0.0);
+    }
+
+    #[test]
+    fn test_scorers_prefer_english() {
+        let english = b"Now that the party is jumping";
+        let garbage = b"\x01\x7fZq\x00\x9cNow\x88\x10xv\xf0zzjQ\x02\x03\xfe\xfd!";
+        let scorers: Vec<Box<dyn Scorer>> = vec![
+            Box::new(Unigram::english()),
+            Box::new(ChiSquared::english()),
+            Box::new(Ngrams::english_bigrams()),
+            Box::new(Ngrams::english_trigrams()),
+            Box::new(PrintablePenalty::new(score, 100.0))
+        ];
+        for scorer in &scorers {
+            assert!(scorer.score(english) < scorer.score(garbage));
+            assert!(!scorer.score(b"").is_nan());
+        }
+        assert_eq!(ChiSquared::english().score(b""), f32::INFINITY);
+        assert!(!ChiSquared::english().score(b"1234 !?").is_nan());
+    }
+
+    #[test]
+    fn test_printable_ratio() {
+        assert_eq!(printable_ratio(b"abc\n"), 1.0);
+        assert_eq!(printable_ratio(b"ab\x00\xff"), 0.5);
+    }
+
+    #[test]
+    fn test_letter_freqs() {
+        let freqs = english_letter_freqs();
+        assert_eq!(freqs.len(), 26);
+        assert_eq!(freqs[4], 12.702);
+        assert_eq!(freqs[25], 0.074);
+        assert_eq!(score(b"etaoin"), letter_score(b"etaoin", &freqs));
+        assert!(parse_letter_freqs("a\tlots\n").is_err());
+
+        let trained = Model::train(ENGLISH_CORPUS).letter_freqs();
+        assert!(diff(&trained, &freqs).unwrap() < 20.0);
+    }
+
+    #[test]
+    fn test_model_round_trip() {
+        let model = Model::train(include_bytes!("../data/rollin.txt"));
+        assert_eq!(model.count(b" "), 26);
+        let path = ::std::env::temp_dir().join("cryptopals_test_model.txt");
+        model.save(&path).unwrap();
+        let loaded = Model::load(&path).unwrap();
+        assert_eq!(loaded, model);
+        assert!(Model::from_bytes(b"6162\tmany\n").is_err());
+        assert!(Model::from_bytes(b"6g\t1\n").is_err());
+    }
+
+    #[test]
+    fn test_model_scoring() {
+        let json = Model::train(
+            br#"{"id": 1, "name": "alice", "tags": ["a", "b"]}
+                {"id": 2, "name": "bob", "admin": false, "tags": []}
+                {"id": 3, "name": "carol", "admin": true, "tags": ["c"]}"#);
+        let english = Model::train(ENGLISH_CORPUS);
+        let record = br#"{"id": 4, "name": "dave", "admin": false}"#;
+        let prose = b"It is a truth universally acknowledged";
+        let trigrams = json.ngrams(3);
+        assert!(trigrams.score(record) < trigrams.score(prose));
+        let trigrams = english.ngrams(3);
+        assert!(trigrams.score(prose) < trigrams.score(record));
+    }
+
+    #[test]
+    fn test_classifier() {
+        let classifier = Classifier::new();
+        let classify = |s: &[u8]| classifier.classify(s).0;
+        assert_eq!(classify(&ENGLISH_CORPUS[..300]), Profile::English);
+        assert_eq!(classify(
+            "Als Gregor Samsa eines Morgens aus unruhigen Träumen erwachte, \
+             fand er sich in seinem Bett zu einem ungeheueren Ungeziefer \
+             verwandelt. Er lag auf seinem panzerartig harten Rücken und sah, \
+             wenn er den Kopf ein wenig hob, seinen gewölbten, braunen Bauch."
+                .as_bytes()), Profile::German);
+        assert_eq!(classify(
+            "Longtemps, je me suis couché de bonne heure. Parfois, à peine ma \
+             bougie éteinte, mes yeux se fermaient si vite que je n'avais pas \
+             le temps de me dire: je m'endors. Et, une demi-heure après, la \
+             pensée qu'il était temps de chercher le sommeil m'éveillait."
+                .as_bytes()), Profile::French);
+        assert_eq!(classify(
+            "En un lugar de la Mancha, de cuyo nombre no quiero acordarme, no \
+             ha mucho tiempo que vivía un hidalgo de los de lanza en astillero, \
+             adarga antigua, rocín flaco y galgo corredor. Una olla de algo más \
+             vaca que carnero, salpicón las más noches, duelos y quebrantos."
+                .as_bytes()), Profile::Spanish);
+        assert_eq!(classify(include_bytes!("../data/6.txt")), Profile::Base64);
+        assert_eq!(classify(
+            br#"{"email": "foo@bar.com", "uid": 10, "role": "user", "tags": []}"#),
+            Profile::Json);
+        assert_eq!(classify(b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR"), Profile::Magic("PNG"));
+        let noise: Vec<u8> = (0..512u32).map(|i| (i * 167 + i / 256 * 13) as u8).collect();
+        assert_eq!(classify(&noise), Profile::Binary);
+
+        let no_magic = Classifier::new().with_magic(false);
+        assert_ne!(no_magic.classify(b"%PDF-1.4\n").0, Profile::Magic("PDF"));
+    }
 }
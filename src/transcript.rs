@@ -0,0 +1,329 @@
+use codec::*;
+use oracle::*;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::from_utf8;
+
+// One query and what came back. Padding oracles answer with a single byte,
+// 1 for good padding and 0 for bad.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub input: Vec<u8>,
+    pub output: Result<Vec<u8>, String>
+}
+
+// Saved as text, one `<hex input><TAB><hex output>` line per query. Errors
+// are written as `!` followed by the message.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Transcript {
+    pub queries: Vec<Query>
+}
+
+impl Transcript {
+    pub fn new() -> Self {
+        Transcript { queries: Vec::new() }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for query in &self.queries {
+            out.extend(base16_encode(&query.input));
+            out.push(b'\t');
+            match query.output {
+                Ok(ref bytes) => out.extend(base16_encode(bytes)),
+                Err(ref err) => {
+                    out.push(b'!');
+                    out.extend(err.replace('\n', " ").bytes());
+                }
+            }
+            out.push(b'\n');
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut queries = Vec::new();
+        for (line_ix, line) in bytes.split(|&b| b == b'\n').enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let tab = line.iter().position(|&b| b == b'\t')
+                .ok_or_else(|| format!("Malformed transcript on line {}", line_ix + 1))?;
+            let output = &line[tab + 1..];
+            let output = if output.first() == Some(&b'!') {
+                Err(from_utf8(&output[1..]).map_err(|err| err.to_string())?.to_string())
+            } else {
+                Ok(base16_decode(output)?)
+            };
+            queries.push(Query { input: base16_decode(&line[..tab])?, output: output });
+        }
+        Ok(Transcript { queries: queries })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        File::create(path)?.write_all(&self.to_bytes())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        Transcript::from_bytes(&bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+// Wraps any oracle, counting (with oracle::Counted) and writing down every
+// query. With a budget, it stops passing queries on once the budget is
+// spent. The try_ methods say so with an error. Through the oracle traits,
+// decryption gets the error and a padding query gets bad padding, with the
+// error kept for `error` to report once the attack has given up. Encryption
+// has nothing sensible to refuse with (an attack handed no ciphertext would
+// only fail somewhere less obvious), so it panics with the error.
+pub struct Recorder<O> {
+    inner: Counted<O>,
+    budget: Option<usize>,
+    transcript: RefCell<Transcript>,
+    error: RefCell<Option<String>>
+}
+
+impl<O> Recorder<O> {
+    pub fn new(inner: O) -> Self {
+        Recorder {
+            inner: Counted::new(inner),
+            budget: None,
+            transcript: RefCell::new(Transcript::new()),
+            error: RefCell::new(None)
+        }
+    }
+
+    pub fn with_budget(mut self, budget: usize) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn logged(mut self) -> Self {
+        self.inner = self.inner.logged();
+        self
+    }
+
+    pub fn queries(&self) -> usize {
+        self.inner.queries()
+    }
+
+    pub fn transcript(&self) -> Transcript {
+        self.transcript.borrow().clone()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.transcript.borrow().save(path)
+    }
+
+    // The first query that was refused, if any.
+    pub fn error(&self) -> Option<String> {
+        self.error.borrow().clone()
+    }
+
+    fn check_budget(&self) -> Result<(), String> {
+        match self.budget {
+            Some(budget) if self.queries() >= budget =>
+                Err(format!("query budget of {} exhausted", budget)),
+            _ => Ok(())
+        }
+    }
+
+    fn record(&self, input: &[u8], output: Result<Vec<u8>, String>) {
+        self.transcript.borrow_mut().queries.push(Query { input: input.to_vec(), output: output });
+    }
+}
+
+impl<O> Recorder<O> where O: EncryptionOracle {
+    pub fn try_encrypt(&self, pt: &[u8]) -> Result<Vec<u8>, String> {
+        self.check_budget()?;
+        let ct = self.inner.encrypt(pt);
+        self.record(pt, Ok(ct.clone()));
+        Ok(ct)
+    }
+}
+
+impl<O> Recorder<O> where O: DecryptionOracle {
+    // The outer error is a refused query; the inner one is the oracle's.
+    pub fn try_decrypt(&self, ct: &[u8]) -> Result<Result<Vec<u8>, String>, String> {
+        self.check_budget()?;
+        let pt = self.inner.decrypt(ct);
+        self.record(ct, pt.clone());
+        Ok(pt)
+    }
+}
+
+impl<O> Recorder<O> where O: PaddingOracle {
+    pub fn try_padding_ok(&self, ct: &[u8]) -> Result<bool, String> {
+        self.check_budget()?;
+        let ok = self.inner.padding_ok(ct);
+        self.record(ct, Ok(vec![ok as u8]));
+        Ok(ok)
+    }
+}
+
+impl<O> EncryptionOracle for Recorder<O> where O: EncryptionOracle {
+    fn encrypt(&self, pt: &[u8]) -> Vec<u8> {
+        self.try_encrypt(pt).unwrap_or_else(|err| panic!("{}", refused(&self.error, err)))
+    }
+}
+
+impl<O> DecryptionOracle for Recorder<O> where O: DecryptionOracle {
+    fn decrypt(&self, ct: &[u8]) -> Result<Vec<u8>, String> {
+        self.try_decrypt(ct).unwrap_or_else(|err| Err(refused(&self.error, err)))
+    }
+}
+
+impl<O> PaddingOracle for Recorder<O> where O: PaddingOracle {
+    fn padding_ok(&self, ct: &[u8]) -> bool {
+        refuse(&self.error, self.try_padding_ok(ct)).unwrap_or(false)
+    }
+}
+
+// Keeps the first refusal, for Recorder::error and Replay::error.
+fn refused(error: &RefCell<Option<String>>, err: String) -> String {
+    let mut error = error.borrow_mut();
+    if error.is_none() {
+        *error = Some(err.clone());
+    }
+    err
+}
+
+fn refuse<T>(error: &RefCell<Option<String>>, answer: Result<T, String>) -> Option<T> {
+    answer.map_err(|err| refused(error, err)).ok()
+}
+
+// Answers queries from a recorded transcript, with no key involved. A
+// deterministic attack asks the same questions again, so any query that
+// isn't in the transcript means the attack has changed. That's an error
+// from the try_ methods, and a refusal or panic from the oracle traits, as
+// with an exhausted Recorder. Answers are looked up by input, so a transcript should
+// only hold queries of one kind.
+pub struct Replay {
+    answers: HashMap<Vec<u8>, Result<Vec<u8>, String>>,
+    error: RefCell<Option<String>>
+}
+
+impl Replay {
+    pub fn new(transcript: &Transcript) -> Self {
+        let answers = transcript.queries.iter()
+            .map(|query| (query.input.clone(), query.output.clone()))
+            .collect();
+        Replay { answers: answers, error: RefCell::new(None) }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Replay::new(&Transcript::load(path)?))
+    }
+
+    // The first query that wasn't in the transcript, if any.
+    pub fn error(&self) -> Option<String> {
+        self.error.borrow().clone()
+    }
+
+    fn answer(&self, input: &[u8]) -> Result<Result<Vec<u8>, String>, String> {
+        self.answers.get(input).cloned().ok_or_else(|| format!(
+            "query {} is not in the transcript",
+            String::from_utf8_lossy(&base16_encode(input))))
+    }
+
+    pub fn try_encrypt(&self, pt: &[u8]) -> Result<Vec<u8>, String> {
+        self.answer(pt)?.map_err(|err| format!("transcript has an error for an encryption query: {}", err))
+    }
+
+    pub fn try_decrypt(&self, ct: &[u8]) -> Result<Result<Vec<u8>, String>, String> {
+        self.answer(ct)
+    }
+
+    pub fn try_padding_ok(&self, ct: &[u8]) -> Result<bool, String> {
+        Ok(self.answer(ct)? == Ok(vec![1]))
+    }
+}
+
+impl EncryptionOracle for Replay {
+    fn encrypt(&self, pt: &[u8]) -> Vec<u8> {
+        self.try_encrypt(pt).unwrap_or_else(|err| panic!("{}", refused(&self.error, err)))
+    }
+}
+
+impl DecryptionOracle for Replay {
+    fn decrypt(&self, ct: &[u8]) -> Result<Vec<u8>, String> {
+        self.try_decrypt(ct).unwrap_or_else(|err| Err(refused(&self.error, err)))
+    }
+}
+
+impl PaddingOracle for Replay {
+    fn padding_ok(&self, ct: &[u8]) -> bool {
+        refuse(&self.error, self.try_padding_ok(ct)).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_replay() {
+        let oracle = Oracle::new(Mode::CBC).with_suffix(b"secret");
+        let recorder = Recorder::new(oracle.clone());
+        let ct = recorder.encrypt(b"hello");
+        let path = ::std::env::temp_dir().join("cryptopals_test_transcript.txt");
+        recorder.save(&path).unwrap();
+        let replay = Replay::load(&path).unwrap();
+        assert_eq!(replay.encrypt(b"hello"), ct);
+
+        let recorder = Recorder::new(oracle.clone());
+        let mut bad = ct.clone();
+        bad[15] ^= 1;
+        let pt = recorder.decrypt(&ct);
+        let err = recorder.decrypt(&bad);
+        assert!(err.is_err());
+        assert_eq!(recorder.queries(), 2);
+
+        let transcript = recorder.transcript();
+        assert_eq!(Transcript::from_bytes(&transcript.to_bytes()).unwrap(), transcript);
+        let replay = Replay::new(&transcript);
+        assert_eq!(replay.decrypt(&ct), pt);
+        assert_eq!(replay.decrypt(&bad), err);
+
+        let recorder = Recorder::new(oracle.clone());
+        assert!(recorder.padding_ok(&ct));
+        assert!(!recorder.padding_ok(&bad));
+        let replay = Replay::new(&recorder.transcript());
+        assert!(replay.padding_ok(&ct));
+        assert!(!replay.padding_ok(&bad));
+        assert!(Transcript::from_bytes(b"41 no tab\n").is_err());
+    }
+
+    #[test]
+    fn test_budget() {
+        let recorder = Recorder::new(|pt: &[u8]| pt.to_vec()).with_budget(2);
+        assert_eq!(recorder.encrypt(b"one"), b"one".to_vec());
+        assert_eq!(recorder.try_encrypt(b"two"), Ok(b"two".to_vec()));
+        assert_eq!(recorder.error(), None);
+        assert_eq!(recorder.try_encrypt(b"three"), Err("query budget of 2 exhausted".to_string()));
+        assert_eq!(recorder.queries(), 2);
+
+        let replay = Replay::new(&recorder.transcript());
+        assert_eq!(replay.try_encrypt(b"two"), Ok(b"two".to_vec()));
+        assert!(replay.try_encrypt(b"three").is_err());
+        assert!(replay.decrypt(b"three").is_err());
+        assert!(!replay.padding_ok(b"three"));
+        assert_eq!(replay.error(), Some("query 7468726565 is not in the transcript".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "query budget of 1 exhausted")]
+    fn test_exhausted_encrypt() {
+        let recorder = Recorder::new(|pt: &[u8]| pt.to_vec()).with_budget(1);
+        recorder.encrypt(b"one");
+        recorder.encrypt(b"two");
+    }
+}
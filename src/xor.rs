@@ -1,7 +1,7 @@
 use stats::*;
 
-use std::f32;
-use itertools::zip;
+use std::cmp::Ordering;
+use itertools::{zip, Itertools};
 
 pub fn fixed_xor(a: &[u8], b: &[u8]) -> Option<Vec<u8>> {
     if a.len() != b.len() {
@@ -20,18 +20,98 @@ pub fn repeating_xor(bytes: &[u8], key: &[u8]) -> Vec<u8> {
 }
 
 pub fn decrypt_single_byte_xor(bytes: &[u8]) -> (Vec<u8>, u8) {
-    let mut best_score = (0, f32::INFINITY);
-    for key in 0..=255 {
-        let ch = vec![key; bytes.len()];
-        let x = fixed_xor(bytes, &ch).expect("ack");
-        let s = score(&x);
-        if best_score.1 > s {
-            best_score = (key, s);
-        }
+    decrypt_single_byte_xor_with(bytes, &score)
+}
+
+pub fn decrypt_single_byte_xor_with<S>(bytes: &[u8], scorer: &S) -> (Vec<u8>, u8)
+    where S: Scorer {
+    let (plaintext, key, _) = rank_single_byte_xor(bytes, scorer, 1).remove(0);
+    (plaintext, key)
+}
+
+// The `n` best keys as (plaintext, key, score), best first. Ties go to the
+// lower key.
+pub fn rank_single_byte_xor<S>(bytes: &[u8], scorer: &S, n: usize) -> Vec<(Vec<u8>, u8, f32)>
+    where S: Scorer {
+    let mut ranked: Vec<(Vec<u8>, u8, f32)> = (0..=255)
+        .map(|key| {
+            let x = fixed_xor(bytes, &vec![key; bytes.len()]).expect("ack");
+            let s = scorer.score(&x);
+            (x, key, s)
+        })
+        .collect();
+    ranked.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal));
+    ranked.truncate(n);
+    ranked
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct XorCandidate {
+    pub line: usize,
+    pub key: u8,
+    pub plaintext: Vec<u8>,
+    pub score: f32
+}
+
+// Find the `n` lines most likely to be single-byte XORed, best first. Line
+// numbers start at 1, the way an editor would show them.
+pub fn find_single_byte_xor<T, S>(lines: &[T], scorer: &S, n: usize) -> Vec<XorCandidate>
+    where T: AsRef<[u8]>, S: Scorer {
+    let mut candidates: Vec<XorCandidate> = lines.iter()
+        .enumerate()
+        .map(|(ix, line)| {
+            let (plaintext, key, score) =
+                rank_single_byte_xor(line.as_ref(), scorer, 1).remove(0);
+            XorCandidate { line: ix + 1, key: key, plaintext: plaintext, score: score }
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.score.partial_cmp(&b.score).unwrap_or(Ordering::Equal));
+    candidates.truncate(n);
+    candidates
+}
+
+// Split `s` into `width` columns, such that every byte in a column was
+// XORed with the same byte of a repeating key.
+pub fn transpose(s: &[u8], width: usize) -> Vec<Vec<u8>> {
+    let mut buffer = Vec::new();
+    for i in 0..width {
+        let chunk: Vec<u8> = s
+            .iter()
+            .skip(i)
+            .step(width)
+            .cloned()
+            .collect();
+        buffer.push(chunk);
     }
+    buffer
+}
+
+// Recover a repeating XOR key of the given size, one column at a time.
+pub fn break_repeating_xor<S>(bytes: &[u8], keysize: usize, scorer: &S) -> Vec<u8>
+    where S: Scorer {
+    transpose(bytes, keysize)
+        .iter()
+        .map(|column| decrypt_single_byte_xor_with(column, scorer).1)
+        .collect()
+}
+
+// Break the XOR as usual, then report which of the classifier's profiles
+// the plaintext fits best.
+pub fn decrypt_single_byte_xor_classified(bytes: &[u8], classifier: &Classifier)
+    -> (Vec<u8>, u8, Profile) {
+    let (plaintext, key) = decrypt_single_byte_xor_with(bytes, classifier);
+    let profile = classifier.classify(&plaintext).0;
+    (plaintext, key, profile)
+}
 
-    let w = fixed_xor(bytes, &vec![best_score.0; bytes.len()]).unwrap();
-    (w, best_score.0)
+// The columns are scored on byte frequencies only, and the whole plaintext
+// is classified once the key is known.
+pub fn break_repeating_xor_classified(bytes: &[u8], keysize: usize,
+                                      classifier: &Classifier) -> (Vec<u8>, Profile) {
+    let columns = classifier.clone().with_magic(false);
+    let key = break_repeating_xor(bytes, keysize, &columns);
+    let profile = classifier.classify(&repeating_xor(bytes, &key)).0;
+    (key, profile)
 }
 
 pub fn hamming(a: &[u8], b: &[u8]) -> Option<u64> {
@@ -50,4 +130,66 @@ mod tests {
                            b"wokka wokka!!!");
         assert_eq!(test.unwrap(), 37);
     }
+
+    #[test]
+    fn test_single_byte_xor_scorers() {
+        // Scoring on letters alone can't tell "Now" from "nOW", since
+        // flipping 0x20 just swaps case.
+        let plaintext = b"Now that the party is jumping\n";
+        let ciphertext = repeating_xor(plaintext, &[0x35]);
+        assert_eq!(decrypt_single_byte_xor(&ciphertext).1, 0x35 ^ 0x20);
+        for key in &[
+            decrypt_single_byte_xor_with(&ciphertext, &Unigram::english()).1,
+            decrypt_single_byte_xor_with(&ciphertext, &ChiSquared::english()).1,
+            decrypt_single_byte_xor_with(&ciphertext, &Ngrams::english_bigrams()).1
+        ] {
+            assert_eq!(*key, 0x35);
+        }
+    }
+
+    #[test]
+    fn test_break_repeating_xor() {
+        let plaintext = &ENGLISH_CORPUS[..1000];
+        let ciphertext = repeating_xor(plaintext, b"ICE ICE BABY");
+        let key = break_repeating_xor(&ciphertext, 12, &ChiSquared::english());
+        assert_eq!(key, b"ICE ICE BABY");
+    }
+
+    #[test]
+    fn test_xor_classified() {
+        let classifier = Classifier::new();
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR\x00\x00\x01\x00";
+        let (plaintext, key, profile) =
+            decrypt_single_byte_xor_classified(&repeating_xor(png, &[0x5a]), &classifier);
+        assert_eq!((&plaintext[..], key, profile), (&png[..], 0x5a, Profile::Magic("PNG")));
+
+        let plaintext = &ENGLISH_CORPUS[..1000];
+        let ciphertext = repeating_xor(plaintext, b"VANILLA");
+        let (key, profile) = break_repeating_xor_classified(&ciphertext, 7, &classifier);
+        assert_eq!((&key[..], profile), (&b"VANILLA"[..], Profile::English));
+    }
+
+    #[test]
+    fn test_rank_single_byte_xor() {
+        let ciphertext = repeating_xor(b"Cooking MC's like a pound of bacon", b"X");
+        let ranked = rank_single_byte_xor(&ciphertext, &ChiSquared::english(), 5);
+        assert_eq!(ranked.len(), 5);
+        assert_eq!(ranked[0].1, b'X');
+        assert_eq!(ranked[0].0, b"Cooking MC's like a pound of bacon");
+        assert!(ranked.windows(2).all(|w| w[0].2 <= w[1].2));
+    }
+
+    #[test]
+    fn test_find_single_byte_xor() {
+        let lines = vec![
+            b"\x0b\x2f\x99\x74\x00\xc1\x8e\x52\x33\x19\xf0\xaa".to_vec(),
+            repeating_xor(b"Now that the party", b"5"),
+            b"\xde\xad\xbe\xef\x01\x02\x03\x04\x05\x06\x07\x08".to_vec()
+        ];
+        let found = find_single_byte_xor(&lines, &ChiSquared::english(), 2);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].line, 2);
+        assert_eq!(found[0].key, b'5');
+        assert_eq!(found[0].plaintext, b"Now that the party");
+    }
 }